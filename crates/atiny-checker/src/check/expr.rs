@@ -2,9 +2,9 @@
 
 use super::Check;
 use crate::infer::Infer;
-use crate::{context::Ctx, types::*, unify::unify};
+use crate::{context::Ctx, types::*, unify::subsume};
 
-use atiny_tree::r#abstract::Expr;
+use atiny_tree::r#abstract::{Expr, ExprKind};
 
 type Elaborated = atiny_tree::elaborated::Expr<Type>;
 
@@ -15,8 +15,44 @@ impl Check<'_> for &Expr {
     fn check(self, mut ctx: Self::Context, expected: Type) -> Self::Result {
         ctx.set_position(self.location);
 
-        let (infer, elaborated) = self.infer(ctx.clone());
-        unify(ctx, infer, expected);
-        elaborated
+        match (&self.data, &*expected.clone().flatten()) {
+            // The expression must have the type for every type of the forall, so they are replaced
+            // by skolems that are only equal to themselves.
+            (_, MonoType::Forall(scheme)) => {
+                let ctx = ctx.level_up();
+                let expected = ctx.skolemize(scheme);
+                self.check(ctx, expected)
+            }
+
+            (ExprKind::Abstraction(params, body), MonoType::Arrow(..)) => {
+                let err_count = ctx.err_count();
+                let (typ, elaborated) = ctx.abstraction(params, body, Some(expected.clone()));
+
+                if ctx.err_count() != err_count {
+                    ctx.errors = Default::default();
+                }
+
+                subsume(ctx, typ, expected);
+                elaborated
+            }
+
+            (ExprKind::Block(statements), _) => ctx.block(statements, Some(expected)).1,
+
+            (ExprKind::Match(e, clauses), _) => ctx.match_expr(e, clauses, Some(expected)).1,
+
+            // An expression with errors would only repeat them as a mismatch with the expected
+            // type, so the mismatch only fills the holes.
+            _ => {
+                let err_count = ctx.err_count();
+                let (infer, elaborated) = self.infer(ctx.clone());
+
+                if ctx.err_count() != err_count {
+                    ctx.errors = Default::default();
+                }
+
+                subsume(ctx, infer, expected);
+                elaborated
+            }
+        }
     }
 }
@@ -0,0 +1,234 @@
+//! Solves the constraints of the type classes.
+//!
+//! Every variable that is instantiated wants the constraints of its type scheme. They are solved
+//! by the instances, by the constraints that the function receives or they are generalized
+//! together with the bindings. A constraint on a hole that can't be generalized is ambiguous, and
+//! the hole defaults to [DEFAULT_TYPE] when all of its classes have an instance for it.
+
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use atiny_location::ByteRange;
+use atiny_tree::elaborated::{Dictionary, Symbol};
+
+use crate::{context::Ctx, types::*};
+
+/// The type of the ambiguous holes with constraints.
+pub const DEFAULT_TYPE: &str = "Int";
+
+/// A constraint that must be solved, together with the dictionary that proves it.
+#[derive(Debug, Clone)]
+pub struct Wanted {
+    pub predicate: Predicate,
+    pub dictionary: Rc<RefCell<Option<Dictionary>>>,
+    pub location: ByteRange,
+}
+
+impl Wanted {
+    fn solve(&self, dictionary: Dictionary) {
+        *self.dictionary.borrow_mut() = Some(dictionary);
+    }
+}
+
+/// A constraint that is proved by a dictionary that the function receives.
+#[derive(Debug, Clone)]
+pub struct Given {
+    pub predicate: Predicate,
+    pub symbol: Symbol,
+}
+
+impl Given {
+    pub fn new(predicate: Predicate) -> Self {
+        let symbol = Symbol(predicate.symbol());
+        Self { predicate, symbol }
+    }
+
+    /// A given for a constraint on a hole that is generalized at a level. It's named after the
+    /// level and its position, so the dictionaries of nested generalizations don't shadow each
+    /// other.
+    fn quantified(predicate: Predicate, level: usize, index: usize) -> Self {
+        let symbol = Symbol(format!("#{}.{}.{}", predicate.class, level, index));
+        Self { predicate, symbol }
+    }
+}
+
+impl Ctx {
+    /// Wants the constraints of a scheme instantiated with the types, returning the dictionaries
+    /// that will prove them.
+    pub fn want(&self, scheme: &TypeScheme, types: &[Type]) -> Vec<Dictionary> {
+        scheme
+            .constraints_with(types)
+            .into_iter()
+            .map(|predicate| {
+                let dictionary = Rc::new(RefCell::new(None));
+
+                self.wanted.borrow_mut().push(Wanted {
+                    predicate,
+                    dictionary: dictionary.clone(),
+                    location: self.location,
+                });
+
+                Dictionary::Hole(dictionary)
+            })
+            .collect()
+    }
+
+    /// Marks where the constraints that are wanted from now on start, to be solved by
+    /// [Self::solve].
+    pub fn wanted_mark(&self) -> usize {
+        self.wanted.borrow().len()
+    }
+
+    /// The holes above the level of the context that the constraints wanted since the mark are
+    /// on.
+    pub fn wanted_holes(&self, mark: usize) -> Vec<Ref> {
+        let wanted = self.wanted.borrow();
+        let types = wanted[mark..]
+            .iter()
+            .map(|wanted| wanted.predicate.typ.clone());
+        MonoType::holes(&types.collect::<Vec<_>>(), self)
+    }
+
+    /// Solves the constraints wanted since the mark with the instances and the `givens`.
+    ///
+    /// The constraints on holes above the level of the context that appear in the `generalized`
+    /// types are returned to be generalized with them, the other ones are ambiguous. The ones on
+    /// holes of lower levels are left to the enclosing declaration, unless it's the `top_level`.
+    pub fn solve(
+        &self,
+        mark: usize,
+        givens: &[Given],
+        generalized: &[Type],
+        top_level: bool,
+    ) -> Vec<Given> {
+        let wanted = self.wanted.borrow_mut().split_off(mark);
+        let mut pending = VecDeque::from(wanted);
+        let mut quantified: Vec<Given> = Vec::new();
+        let mut deferred = Vec::new();
+        let mut ambiguous: Vec<(Ref, Vec<Wanted>)> = Vec::new();
+
+        loop {
+            while let Some(wanted) = pending.pop_front() {
+                let given = givens.iter().chain(&quantified).find(|given| {
+                    given.predicate.class == wanted.predicate.class
+                        && given.predicate.typ.is_same(&wanted.predicate.typ)
+                });
+
+                if let Some(given) = given {
+                    wanted.solve(Dictionary::Param(given.symbol.clone()));
+                    continue;
+                }
+
+                let typ = wanted.predicate.typ.clone().flatten();
+
+                match (&*typ, typ.head_hole()) {
+                    (MonoType::Application(name, args), _) => {
+                        self.resolve(&wanted, name, args, &mut pending);
+                    }
+                    (MonoType::Error, _) => {}
+                    (_, Some((hole, level)))
+                        if level > self.level
+                            && generalized.iter().any(|typ| typ.contains_hole(&hole)) =>
+                    {
+                        let index = quantified.len();
+                        let given = Given::quantified(wanted.predicate.clone(), self.level, index);
+                        wanted.solve(Dictionary::Param(given.symbol.clone()));
+                        quantified.push(given);
+                    }
+                    (_, Some((_, level))) if level <= self.level && !top_level => {
+                        deferred.push(wanted);
+                    }
+                    (_, Some((hole, _))) => {
+                        match ambiguous.iter_mut().find(|(other, _)| *other == hole) {
+                            Some((_, wanted_vec)) => wanted_vec.push(wanted),
+                            None => ambiguous.push((hole, vec![wanted])),
+                        }
+                    }
+                    (_, None) => self.no_instance(&wanted),
+                }
+            }
+
+            if ambiguous.is_empty() {
+                break;
+            }
+
+            for (hole, wanted) in std::mem::take(&mut ambiguous) {
+                self.default(&hole, wanted, &mut pending);
+            }
+        }
+
+        self.wanted.borrow_mut().extend(deferred);
+        quantified
+    }
+
+    /// Solves a constraint by the instance of its type constructor, which wants the constraints of
+    /// the instance for the arguments of the type.
+    fn resolve(&self, wanted: &Wanted, name: &str, args: &[Type], pending: &mut VecDeque<Wanted>) {
+        let class = self.signatures.classes.get(&wanted.predicate.class);
+
+        let Some(instance) = class.and_then(|class| class.instances.get(name)) else {
+            return self.no_instance(wanted);
+        };
+
+        let substitutions = instance
+            .params
+            .iter()
+            .cloned()
+            .zip(args.iter().cloned())
+            .collect();
+
+        let dictionaries = instance
+            .constraints
+            .iter()
+            .map(|predicate| {
+                let dictionary = Rc::new(RefCell::new(None));
+
+                pending.push_back(Wanted {
+                    predicate: predicate.substitute(&substitutions),
+                    dictionary: dictionary.clone(),
+                    location: wanted.location,
+                });
+
+                Dictionary::Hole(dictionary)
+            })
+            .collect();
+
+        wanted.solve(Dictionary::Instance(
+            Symbol(instance.symbol.clone()),
+            dictionaries,
+        ));
+    }
+
+    /// Fills an ambiguous hole with the [DEFAULT_TYPE] if all of its classes have an instance for
+    /// it, otherwise the ambiguity is reported.
+    fn default(&self, hole: &Ref, wanted: Vec<Wanted>, pending: &mut VecDeque<Wanted>) {
+        let defaults = wanted.iter().all(|wanted| {
+            let instance = self
+                .signatures
+                .classes
+                .get(&wanted.predicate.class)
+                .and_then(|class| class.instances.get(DEFAULT_TYPE));
+
+            let is_hole = matches!(&*wanted.predicate.typ.clone().flatten(), MonoType::Hole(_));
+            is_hole && instance.is_some()
+        });
+
+        if defaults {
+            hole.fill(MonoType::typ(DEFAULT_TYPE.to_string()));
+            pending.extend(wanted);
+        } else {
+            let mut ctx = self.clone();
+            ctx.set_position(wanted[0].location);
+            let predicate = Names::new(&[&wanted[0].predicate.typ]).predicate(&wanted[0].predicate);
+            ctx.error(format!(
+                "ambiguous type variable in the constraint '{predicate}'"
+            ));
+        }
+    }
+
+    fn no_instance(&self, wanted: &Wanted) {
+        let mut ctx = self.clone();
+        ctx.set_position(wanted.location);
+        let predicate = Names::new(&[&wanted.predicate.typ]).predicate(&wanted.predicate);
+        ctx.error(format!("no instance for '{predicate}'"));
+    }
+}
@@ -3,11 +3,12 @@
 
 use std::{cell::RefCell, fmt::Display, rc::Rc};
 
-use atiny_error::{Error, Message, SugestionKind};
+use atiny_error::{Error, Message, Severity, SugestionKind};
 use atiny_location::ByteRange;
 use atiny_tree::r#abstract::TypeDecl;
 use itertools::Itertools;
 
+use super::constraints::Wanted;
 use super::types::*;
 
 #[derive(Clone, Default, Debug)]
@@ -15,54 +16,107 @@ pub struct Signatures {
     pub types: im_rc::OrdMap<String, TypeSignature>,
     pub values: im_rc::OrdMap<String, DeclSignature>,
     pub fields: im_rc::OrdMap<String, im_rc::OrdSet<String>>,
+    pub classes: im_rc::OrdMap<String, ClassSignature>,
+    pub effects: im_rc::OrdMap<String, EffectSignature>,
 }
 
 impl Display for Signatures {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Types:\n    {}", self.types.values().join("\n    "))?;
-        writeln!(f, "\nValues:\n    {}", self.values.values().join("\n    "))
+        writeln!(f, "\nValues:\n    {}", self.values.values().join("\n    "))?;
+        writeln!(
+            f,
+            "\nClasses:\n    {}",
+            self.classes.values().join("\n    ")
+        )?;
+        writeln!(
+            f,
+            "\nEffects:\n    {}",
+            self.effects.values().join("\n    ")
+        )
     }
 }
 
 #[derive(Clone)]
 pub struct Ctx {
     counter: Rc<RefCell<usize>>,
+
+    /// The ids of the holes, they are apart from the counter of the names so the names don't
+    /// depend on how many holes were created.
+    hole_ids: Rc<RefCell<usize>>,
     pub errors: Rc<RefCell<Vec<Error>>>,
+
+    /// The constraints of the instantiated variables that are not solved yet.
+    pub wanted: Rc<RefCell<Vec<Wanted>>>,
     pub map: im_rc::OrdMap<String, Rc<TypeScheme>>,
-    pub typ_map: im_rc::OrdSet<String>,
+    pub typ_map: im_rc::OrdMap<String, Rc<Kind>>,
     pub location: ByteRange,
     pub level: usize,
     pub signatures: Signatures,
+
+    /// The row of the effects that the expressions can perform, the ones of the function or of
+    /// the handlers around them.
+    pub effects: Type,
 }
 
 impl Default for Ctx {
     fn default() -> Self {
         let mut ctx = Self {
             counter: Default::default(),
+            hole_ids: Default::default(),
             errors: Default::default(),
+            wanted: Default::default(),
             map: Default::default(),
             typ_map: Default::default(),
             location: Default::default(),
             level: Default::default(),
             signatures: Default::default(),
+            effects: MonoType::pure(),
         };
 
+        for name in ["Int", "String", "Char"] {
+            ctx.signatures.types.insert(
+                name.to_string(),
+                TypeSignature::new_opaque(name.to_string()),
+            );
+        }
+
         ctx.signatures.types.insert(
-            "Int".to_string(),
-            TypeSignature::new_opaque("Int".to_string()),
+            REF.to_string(),
+            TypeSignature::new_opaque_constructor(REF.to_string(), vec!["a".to_string()]),
         );
 
         let int = MonoType::typ("Int".to_string());
-        let sig = MonoType::arrow(int.clone(), MonoType::arrow(int.clone(), int)).to_poly();
+        let bool = MonoType::typ("Bool".to_string());
+        let var = MonoType::var("a".to_string());
+
+        let binary = |arg: &Type, ret: &Type| {
+            MonoType::arrow(arg.clone(), MonoType::arrow(arg.clone(), ret.clone()))
+        };
+
+        let sig = binary(&int, &int).to_poly();
+        let compare = binary(&int, &bool).to_poly();
+        let logic = binary(&bool, &bool).to_poly();
+        let equal = TypeScheme::new(vec!["a".to_string()], binary(&var, &bool));
 
         ctx.map.extend([
             ("add".to_string(), sig.clone()),
             ("sub".to_string(), sig.clone()),
             ("mul".to_string(), sig.clone()),
             ("div".to_string(), sig),
+            ("neg".to_string(), int.clone().arrow(int).to_poly()),
+            ("lt".to_string(), compare.clone()),
+            ("le".to_string(), compare.clone()),
+            ("gt".to_string(), compare.clone()),
+            ("ge".to_string(), compare),
+            ("eq".to_string(), equal.clone()),
+            ("neq".to_string(), equal),
+            ("and".to_string(), logic.clone()),
+            ("or".to_string(), logic),
+            ("not".to_string(), bool.clone().arrow(bool).to_poly()),
         ]);
 
-        ctx.extend_type_sigs(Some(TypeDecl::unit()));
+        ctx.extend_type_sigs([TypeDecl::unit(), TypeDecl::bool(), TypeDecl::list()]);
 
         ctx
     }
@@ -77,18 +131,27 @@ impl Ctx {
         }
     }
 
-    /// Extends a context with a type name (for type variables).
+    /// Extends a context with a type name (for type variables), its kind is inferred by its uses.
     pub fn extend_type(&self, name: String) -> Self {
         Self {
-            typ_map: self.typ_map.update(name),
+            typ_map: self.typ_map.update(name, Kind::new_hole()),
             ..self.clone()
         }
     }
 
-    /// Extends a context with a list of type names (for type variables).
+    /// Extends a context with a list of type names (for type variables), their kinds are inferred
+    /// by their uses.
     pub fn extend_types<'a, N: IntoIterator<Item = &'a String>>(&self, names: N) -> Self {
+        let names = names
+            .into_iter()
+            .map(|name| (name.clone(), Kind::new_hole()));
+        self.extend_kinded_types(names.collect())
+    }
+
+    /// Extends a context with type variables of known kinds.
+    pub fn extend_kinded_types(&self, kinds: im_rc::OrdMap<String, Rc<Kind>>) -> Self {
         Self {
-            typ_map: self.typ_map.clone().union(names.into_iter().collect()),
+            typ_map: kinds.union(self.typ_map.clone()),
             ..self.clone()
         }
     }
@@ -108,23 +171,32 @@ impl Ctx {
         self.location = location;
     }
 
+    fn next_id(&self) -> usize {
+        let mut counter = self.counter.borrow_mut();
+        *counter += 1;
+        *counter
+    }
+
     /// Creates a new name for a type variable.
     pub fn new_name(&self) -> String {
-        let counter = {
-            let mut counter = self.counter.borrow_mut();
-            *counter += 1;
-            *counter
-        };
-
+        let counter = self.next_id();
         format!("'{}", (97 + ((counter - 1) % 26)) as u8 as char)
     }
 
+    fn next_hole_id(&self) -> usize {
+        let mut hole_ids = self.hole_ids.borrow_mut();
+        *hole_ids += 1;
+        *hole_ids
+    }
+
     /// Looks up a type variable name in the context.
     pub fn lookup(&self, name: &str) -> Option<Rc<TypeScheme>> {
         self.map.get(name).cloned().or_else(|| {
             self.signatures.values.get(name).map(|decl| match decl {
                 DeclSignature::Function(fun) => fun.entire_type.clone(),
                 DeclSignature::Constructor(decl) => decl.typ.clone(),
+                DeclSignature::Method(method) => method.typ.clone(),
+                DeclSignature::Operation(operation) => operation.typ.clone(),
             })
         })
     }
@@ -134,7 +206,9 @@ impl Ctx {
             .values
             .get(name)
             .and_then(|decl| match decl {
-                DeclSignature::Function(_) => None,
+                DeclSignature::Function(_)
+                | DeclSignature::Method(_)
+                | DeclSignature::Operation(_) => None,
                 DeclSignature::Constructor(cons) => Some(cons.clone()),
             })
     }
@@ -177,6 +251,13 @@ impl Ctx {
             .push(Error::new_dyn(msg, self.location));
     }
 
+    /// Reports a message that doesn't stop the compilation.
+    pub fn info(&self, msg: impl Display + 'static) {
+        self.errors
+            .borrow_mut()
+            .push(Error::new_dyn(msg, self.location).with_severity(Severity::Info));
+    }
+
     pub fn take_errors(&self) -> Option<Vec<Error>> {
         let is_not_empty = { !self.errors.borrow().is_empty() };
 
@@ -187,13 +268,47 @@ impl Ctx {
         })
     }
 
+    /// The number of errors, without the other diagnostics.
     pub fn err_count(&self) -> usize {
-        self.errors.borrow().len()
+        self.errors
+            .borrow()
+            .iter()
+            .filter(|err| err.is_error())
+            .count()
     }
 
     /// Creates a new hole type.
     pub fn new_hole(&self) -> Type {
-        MonoType::new_hole(self.new_name(), self.level)
+        self.new_hole_at(self.level)
+    }
+
+    /// Creates a new hole type of a level.
+    pub fn new_hole_at(&self, level: usize) -> Type {
+        MonoType::new_hole(self.next_hole_id(), self.new_name(), level)
+    }
+
+    /// Creates a new hole for a row of effects. They don't take the names of the type variables
+    /// because they are not shown while they are not known.
+    pub fn new_effect_hole(&self) -> Type {
+        self.new_effect_hole_at(self.level)
+    }
+
+    pub fn new_effect_hole_at(&self, level: usize) -> Type {
+        MonoType::new_hole(self.next_hole_id(), EFFECT_HOLE.to_string(), level)
+    }
+
+    /// Creates skolems of the current level for type variables, they can't be used by the holes of
+    /// the levels below it.
+    pub fn new_skolems(&self, names: &[String]) -> Vec<Type> {
+        names
+            .iter()
+            .map(|name| Rc::new(MonoType::Skolem(name.clone(), self.next_id(), self.level)))
+            .collect()
+    }
+
+    /// Replaces the type variables of a forall by new skolems.
+    pub fn skolemize(&self, scheme: &TypeScheme) -> Type {
+        scheme.instantiate_with(&self.new_skolems(&scheme.names))
     }
 }
 
@@ -50,6 +50,33 @@ impl Witness {
         }
     }
 
+    /// Creates a new witness that contains a record pattern based on the fields that got non
+    /// exhausted. The fields that can be anything are left to the `..`.
+    pub fn expand_record(self, name: &str, fields: &[(String, Type)]) -> Self {
+        if let Self::NonExhaustive(row) = self {
+            let (vec, row) = row.split_vec(fields.len());
+
+            let (wildcards, fields): (Vec<_>, Vec<_>) = fields
+                .iter()
+                .zip(vec)
+                .map(|((name, _), pat)| PatternField {
+                    name: name.clone(),
+                    pat,
+                })
+                .partition(|field| matches!(field.pat.data, PatternKind::Atom(AtomKind::Wildcard)));
+
+            let data = PatternKind::Record(name.to_string(), fields, !wildcards.is_empty());
+
+            let row = row.prepend(Pattern {
+                location: ByteRange::singleton(0),
+                data,
+            });
+            Self::NonExhaustive(row)
+        } else {
+            self
+        }
+    }
+
     /// Checks if a witness is non exhaustive.
     pub fn is_non_exhaustive(&self) -> bool {
         matches!(self, Self::NonExhaustive(_))
@@ -72,9 +99,10 @@ impl Witness {
     }
 }
 
-/// A row is a clause of a pattern match. It contains a list of patterns that are being matched.
+/// A row is a clause of a pattern match. It contains a list of patterns that are being matched and
+/// if the clause has a guard, in which case the row does not cover the values that it matches.
 #[derive(Clone)]
-pub struct Row(Option<usize>, Vec<Pattern>);
+pub struct Row(Option<usize>, Vec<Pattern>, bool);
 
 impl Row {
     /// Checks if a row is empty.
@@ -117,6 +145,17 @@ impl Row {
     }
 
     pub fn specialize(self, ctx: &Ctx, case: Case<()>) -> Vec<Self> {
+        match self.first().data.clone() {
+            // An or-pattern is split into one row for each of its alternatives.
+            PatternKind::Or(left, right) => {
+                let mut rows = self.clone().inline([*left]).specialize(ctx, case.clone());
+                rows.extend(self.inline([*right]).specialize(ctx, case));
+                return rows;
+            }
+            PatternKind::As(_, pat) => return self.inline([*pat]).specialize(ctx, case),
+            _ => {}
+        }
+
         match (self.get_first_pattern(ctx), case) {
             //default_row
             (Case::Wildcard, Case::Wildcard) => vec![self.pop_front()],
@@ -133,10 +172,22 @@ impl Row {
             (Case::Tuple(p), Case::Tuple(t)) if p.len() == t.len() => vec![self.inline(p)],
             (Case::Wildcard, Case::Tuple(t)) => vec![self.inline(vec![wildcard(); t.len()])],
 
+            //specialize_record
+            (Case::Record(p), Case::Record(t)) if p.len() == t.len() => vec![self.inline(p)],
+            (Case::Wildcard, Case::Record(t)) => vec![self.inline(vec![wildcard(); t.len()])],
+
             //specialize_number
             (Case::Int(n1), Case::Int(n2)) if n1 == n2 => vec![self.pop_front()],
             (Case::Wildcard, Case::Int(_)) => vec![self.pop_front()],
 
+            //specialize_string
+            (Case::String(s1), Case::String(s2)) if s1 == s2 => vec![self.pop_front()],
+            (Case::Wildcard, Case::String(_)) => vec![self.pop_front()],
+
+            //specialize_char
+            (Case::Char(c1), Case::Char(c2)) if c1 == c2 => vec![self.pop_front()],
+            (Case::Wildcard, Case::Char(_)) => vec![self.pop_front()],
+
             _ => vec![],
         }
     }
@@ -175,12 +226,12 @@ impl Matrix {
     }
 
     /// It transforms a column into a matrix. This is done by transforming each pattern into a row.
-    pub fn from_column(column: Vec<Pattern>) -> Self {
+    pub fn from_column(column: Vec<(Pattern, bool)>) -> Self {
         Self(
             column
                 .into_iter()
                 .enumerate()
-                .map(|(place, pat)| Row(Some(place), vec![pat]))
+                .map(|(place, (pat, guarded))| Row(Some(place), vec![pat], guarded))
                 .collect(),
         )
     }
@@ -195,13 +246,25 @@ impl Matrix {
         })
     }
 
-    /// Gets the used constructor of a pattern. This is done by checking if the pattern is a
-    /// constructor on the context.
-    fn get_used_constructor(ctx: &Ctx, pat: &PatternKind) -> Option<String> {
+    /// Gets the used constructors of a pattern. This is done by checking if the pattern is a
+    /// constructor on the context. An or-pattern uses the constructors of all of its alternatives.
+    fn get_used_constructor(ctx: &Ctx, pat: &PatternKind) -> Vec<String> {
         match pat {
-            PatternKind::Atom(AtomKind::Identifier(id)) => ctx.lookup_cons(id).map(|_| id.clone()),
-            PatternKind::Constructor(name, _) => Some(name.clone()),
-            _ => None,
+            PatternKind::Atom(AtomKind::Identifier(id)) => ctx
+                .lookup_cons(id)
+                .map(|_| id.clone())
+                .into_iter()
+                .collect(),
+            PatternKind::Constructor(name, _) | PatternKind::Record(name, _, _) => {
+                vec![name.clone()]
+            }
+            PatternKind::Or(left, right) => {
+                let mut names = Self::get_used_constructor(ctx, &left.data);
+                names.extend(Self::get_used_constructor(ctx, &right.data));
+                names
+            }
+            PatternKind::As(_, pat) => Self::get_used_constructor(ctx, &pat.data),
+            _ => Vec::new(),
         }
     }
 
@@ -237,7 +300,10 @@ impl Matrix {
     /// If removes all the rows that are after a row that is completely made out of wildcards or
     /// variables. It is just an optimization.
     pub fn filter_first_match(&mut self, ctx: &Ctx) {
-        let index = self.0.iter().find_position(|row| row.is_all_wildcards(ctx));
+        let index = self
+            .0
+            .iter()
+            .find_position(|row| !row.2 && row.is_all_wildcards(ctx));
         if let Some((index, _)) = index {
             self.0.truncate(index + 1);
         }
@@ -300,17 +366,27 @@ impl Problem {
     /// Creates a new problem. It takes the scrutineer type of the problem, the case that is being
     /// checked and the patterns that are being matched.
     pub fn new(typ: Type, useful: Vec<Pattern>, columns: Vec<Pattern>) -> Self {
+        let columns = columns.into_iter().map(|pat| (pat, false)).collect();
+        Self::with_guards(typ, useful, columns)
+    }
+
+    /// Creates a new problem where each pattern that is being matched tells if it has a guard.
+    pub fn with_guards(typ: Type, useful: Vec<Pattern>, columns: Vec<(Pattern, bool)>) -> Self {
         Self {
             typ: vec![typ],
-            case: Row(None, useful),
+            case: Row(None, useful, false),
             matrix: Matrix::from_column(columns),
         }
     }
 
     /// Checks if the matrix is exhaustive by looking at all the rows and checking if any of them
-    /// is empty (it matches ).
-    fn get_exhaustive_row(&self) -> Option<&Row> {
-        self.matrix.0.iter().find(|row| row.is_empty())
+    /// is empty (it matches ), returning it with its index.
+    fn get_exhaustive_row(&self) -> Option<(usize, &Row)> {
+        self.matrix
+            .0
+            .iter()
+            .enumerate()
+            .find(|(_, row)| row.is_empty())
     }
 
     /// Checks if the problem is empty. A problem is empty if the matrix is empty.
@@ -367,10 +443,65 @@ impl Problem {
                 Witness::Ok(CaseTreeNode::Node(nodes))
             }
 
+            // A record is a product with a single constructor, so it's always destructed.
+            TypeValue::Product(fields) => {
+                let pat = vec![wildcard(); fields.len()];
+                let witness = self.specialize_record(ctx, type_sig, type_args, pat);
+                witness.expand_record(name, &fields)
+            }
+
             // Opaque types will never be splitted because they are either incomplete or
             // they fall in the is_all_wildcards case
             TypeValue::Opaque => unreachable!("Opaque types are impossible here"),
-            TypeValue::Product(_) => unreachable!("Record types are impossible here"),
+
+            // The types are flattened, so the aliases are already replaced by their expansion.
+            TypeValue::Alias(_) => unreachable!("aliases are expanded before"),
+        }
+    }
+
+    /// Specializes into a new problem where the first type is replaced by the types of the fields
+    /// of a record, in the order that they are declared.
+    fn specialize_record(
+        self,
+        ctx: &Ctx,
+        type_sig: &TypeSignature,
+        type_args: &[Type],
+        pat: Vec<Pattern>,
+    ) -> Witness {
+        let fields = type_sig.get_product().unwrap_or_default();
+
+        let types = fields.iter().map(|(_, typ)| {
+            TypeScheme {
+                names: type_sig.params.clone(),
+                constraints: Vec::new(),
+                mono: typ.clone(),
+            }
+            .instantiate_with(type_args)
+        });
+
+        let case = Case::Record(vec![(); fields.len()]);
+
+        match self.specialize(ctx, types, pat, case) {
+            Witness::Ok(tree) => {
+                let names = fields
+                    .iter()
+                    .map(|(name, _)| Symbol(name.clone()))
+                    .collect();
+                Witness::Ok(CaseTreeNode::Record(names, Box::new(tree)))
+            }
+            witness => witness,
+        }
+    }
+
+    /// Removes a guarded row that matches the case, because the guard may be false and then the
+    /// next rows have to match it. The other rows of the clause are kept, they are the other
+    /// alternatives of its or-patterns.
+    fn fall_through(mut self, ctx: &Ctx, index: usize, place: usize) -> Witness {
+        self.matrix.0.remove(index);
+
+        match self.exhaustiveness(ctx) {
+            Witness::Ok(tree) => Witness::Ok(CaseTreeNode::Guard(place, Box::new(tree))),
+            witness => witness,
         }
     }
 
@@ -389,15 +520,20 @@ impl Problem {
         }
     }
 
-    /// Generates a constructor pattern for the given constructor name.
+    /// Generates a constructor pattern for the given constructor name. The constructor of a record
+    /// is the record itself.
     fn synthesize_constructor(&self, ctx: &Ctx, name: &str) -> Pattern {
-        let cons_sig = ctx.lookup_cons(name).unwrap();
-
-        let args = vec![wildcard(); cons_sig.args.len()];
+        let data = ctx.lookup_cons(name).map_or_else(
+            || PatternKind::Record(name.to_string(), Vec::new(), true),
+            |cons_sig| {
+                let args = vec![wildcard(); cons_sig.args.len()];
+                PatternKind::Constructor(name.to_string(), args)
+            },
+        );
 
         Pattern {
             location: ByteRange::default(),
-            data: PatternKind::Constructor(name.to_string(), args),
+            data,
         }
     }
 
@@ -544,12 +680,40 @@ impl Problem {
         // matches the case)
         if self.is_empty() {
             Witness::NonExhaustive(self.case)
-        } else if let Some(row) = self.get_exhaustive_row().and_then(|x| x.0) {
-            Witness::Ok(CaseTreeNode::Leaf(row))
+        } else if let Some((index, &Row(Some(place), _, guarded))) = self.get_exhaustive_row() {
+            if guarded {
+                self.fall_through(ctx, index, place)
+            } else {
+                Witness::Ok(CaseTreeNode::Leaf(place))
+            }
         } else {
             // The first pattern of the case will guide the specialization of the whole problem.
-            let pat = self.case.first().clone().data;
-            self.match_exhaustiveness(ctx, Case::from_pattern(ctx, pat))
+            match self.case.first().clone().data {
+                // The case of an or-pattern is useful if any of its alternatives is useful.
+                PatternKind::Or(left, right) => {
+                    let mut right_problem = self.clone();
+                    right_problem.case = right_problem.case.inline([*right]);
+
+                    let mut left_problem = self;
+                    left_problem.case = left_problem.case.inline([*left]);
+
+                    let witness = left_problem.exhaustiveness(ctx);
+
+                    if witness.is_non_exhaustive() {
+                        witness
+                    } else {
+                        right_problem.exhaustiveness(ctx)
+                    }
+                }
+
+                PatternKind::As(_, pat) => {
+                    let mut problem = self;
+                    problem.case = problem.case.inline([*pat]);
+                    problem.exhaustiveness(ctx)
+                }
+
+                pat => self.match_exhaustiveness(ctx, Case::from_pattern(ctx, pat)),
+            }
         }
     }
 
@@ -582,12 +746,21 @@ impl Problem {
                 self.specialize_cons(ctx, args, cons, pat)
             }
 
+            (Case::Record(pat), MonoType::Application(name, args)) => {
+                let type_sig = ctx.lookup_type(name).unwrap().clone();
+                self.specialize_record(ctx, &type_sig, args, pat)
+            }
+
             (Case::Tuple(pat), MonoType::Tuple(types)) => {
                 self.specialize(ctx, types.clone(), pat, Case::Tuple(vec![(); types.len()]))
             }
 
             (Case::Int(n), _) => self.specialize(ctx, None, None, Case::Int(n)),
 
+            (Case::String(s), _) => self.specialize(ctx, None, None, Case::String(s)),
+
+            (Case::Char(c), _) => self.specialize(ctx, None, None, Case::Char(c)),
+
             _ => unimplemented!(),
         }
     }
@@ -597,7 +770,10 @@ impl Problem {
 pub enum Case<T: Clone> {
     Constructor(Rc<ConstructorSignature>, Vec<T>),
     Tuple(Vec<T>),
+    Record(Vec<T>),
     Int(u64),
+    String(String),
+    Char(char),
     Wildcard,
 }
 
@@ -605,9 +781,31 @@ impl Case<Pattern> {
     fn from_pattern(ctx: &Ctx, pat: PatternKind) -> Self {
         match pat {
             PatternKind::Atom(atom) => Self::from_atom(ctx, atom),
-            PatternKind::Constructor(name, args) => {
-                let cons = ctx.lookup_cons(&name).unwrap();
-                Self::Constructor(cons, args)
+            // An unbound constructor is reported by the inference, so it matches anything.
+            PatternKind::Constructor(name, args) => ctx
+                .lookup_cons(&name)
+                .map_or_else(|| Self::Wildcard, |cons| Self::Constructor(cons, args)),
+            PatternKind::Record(name, mut fields, _) => {
+                let record = ctx.lookup_type(&name).and_then(TypeSignature::get_product);
+
+                // The fields are sorted in the order of the declaration and the ones that are
+                // not written are wildcards.
+                let args = record
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|(field, _)| {
+                        fields
+                            .iter()
+                            .position(|pat_field| pat_field.name == *field)
+                            .map_or_else(wildcard, |index| fields.swap_remove(index).pat)
+                    })
+                    .collect();
+
+                Self::Record(args)
+            }
+            PatternKind::Error => Self::Wildcard,
+            PatternKind::Or(..) | PatternKind::As(..) => {
+                unreachable!("or and as patterns are expanded before")
             }
         }
     }
@@ -619,6 +817,8 @@ impl Case<Pattern> {
                 .lookup_cons(&name)
                 .map_or_else(|| Self::Wildcard, |cons| Self::Constructor(cons, vec![])),
             AtomKind::Number(number) => Self::Int(number),
+            AtomKind::String(string) => Self::String(string),
+            AtomKind::Char(char) => Self::Char(char),
             AtomKind::Tuple(tuple) => Self::Tuple(tuple),
         }
     }
@@ -0,0 +1,345 @@
+//! Type inference for the declarations of classes and instances.
+//!
+//! The methods of a class are values whose type schemes are constrained by the class. The methods
+//! of an instance are checked against them, and they receive the dictionaries of the constraints of
+//! the instance.
+
+use super::Infer;
+use crate::{check::Check, constraints::Given, context::Ctx, types::*};
+
+use atiny_location::{ByteRange, Located};
+use atiny_tree::{
+    elaborated::{FnBody, Symbol},
+    r#abstract::*,
+};
+use itertools::Itertools;
+use std::{collections::HashSet, iter, rc::Rc};
+
+impl Ctx {
+    pub fn extend_class_sigs(&mut self, classes: impl IntoIterator<Item = Located<ClassDecl>>) {
+        classes.into_iter().for_each(|class| class.infer(self));
+    }
+
+    /// Declares the instances, their methods are checked after the signatures of the functions.
+    pub fn extend_instance_sigs(
+        &mut self,
+        instances: impl IntoIterator<Item = Located<InstanceDecl>>,
+    ) -> Vec<InstanceMethods> {
+        instances
+            .into_iter()
+            .filter_map(|instance| instance.infer(self))
+            .collect()
+    }
+
+    /// Infers a constraint of a `where` clause, it must be on a type variable.
+    pub fn constraint(&self, constraint: &Constraint) -> Option<Predicate> {
+        let mut ctx = self.clone();
+        ctx.set_position(constraint.class.location);
+
+        let Some(class) = self.signatures.classes.get(&constraint.class.data) else {
+            ctx.error(format!("unbound class '{}'", constraint.class.data));
+            return None;
+        };
+
+        let typ = self.check_kind(&constraint.typ, &class.kind);
+
+        match &*typ {
+            MonoType::Var(_) => Some(Predicate {
+                class: class.name.clone(),
+                typ,
+            }),
+            MonoType::Error => None,
+            _ => {
+                ctx.set_position(constraint.typ.location);
+                ctx.error(format!(
+                    "the constraint '{constraint}' must be on a type variable"
+                ));
+                None
+            }
+        }
+    }
+}
+
+/// Declares a class and its methods.
+///
+/// The kind of the parameter of the class is inferred by the types of the methods.
+impl Infer for Located<ClassDecl> {
+    type Context<'a> = &'a mut Ctx;
+    type Return = ();
+
+    fn infer(self, ctx: Self::Context<'_>) -> Self::Return {
+        let location = self.location;
+        let class = self.data;
+
+        if ctx.signatures.classes.contains_key(&class.name) {
+            ctx.set_position(location);
+            ctx.error(format!(
+                "the class '{}' is declared more than once",
+                class.name
+            ));
+            return;
+        }
+
+        let kind = Kind::new_hole();
+        let class_ctx =
+            ctx.extend_kinded_types(im_rc::ordmap! {class.param.clone() => kind.clone()});
+
+        let predicate = Predicate {
+            class: class.name.clone(),
+            typ: MonoType::var(class.param.clone()),
+        };
+
+        let mut methods: Vec<(String, Rc<TypeScheme>)> = Vec::new();
+
+        for Field { name, ty, .. } in class.methods {
+            let declared = ctx
+                .signatures
+                .classes
+                .values()
+                .flat_map(|class| &class.methods);
+
+            if declared.chain(&methods).any(|(method, _)| *method == name) {
+                ctx.set_position(ty.location);
+                ctx.error(format!("the method '{name}' is declared more than once"));
+                continue;
+            }
+
+            // The other type variables of the method are polymorphic too.
+            let mut set = HashSet::new();
+            class_ctx.free_variables(&ty, &mut set);
+
+            let mono = ty.infer(class_ctx.extend_types(set.iter()));
+            let names = iter::once(class.param.clone()).chain(set.into_iter().sorted());
+            let scheme = TypeScheme::qualified(names.collect(), vec![predicate.clone()], mono);
+
+            let sig = MethodSignature {
+                name: name.clone(),
+                class: class.name.clone(),
+                typ: scheme.clone(),
+            };
+
+            ctx.signatures
+                .values
+                .insert(name.clone(), DeclSignature::Method(Rc::new(sig)));
+
+            methods.push((name, scheme));
+        }
+
+        kind.default_holes();
+
+        let sig = ClassSignature {
+            name: class.name.clone(),
+            param: class.param,
+            kind,
+            methods,
+            instances: Default::default(),
+        };
+
+        ctx.signatures.classes.insert(class.name, sig);
+    }
+}
+
+/// The methods of an instance that was declared, they are checked after all the signatures are
+/// known.
+pub struct InstanceMethods {
+    class: ClassSignature,
+    instance: Rc<InstanceSignature>,
+    typ: Type,
+    typ_map: im_rc::OrdMap<String, Rc<Kind>>,
+    location: ByteRange,
+    methods: Vec<FnDecl>,
+}
+
+/// Declares an instance for a type constructor. The instances can't overlap, so there's only one
+/// for each type constructor.
+impl Infer for Located<InstanceDecl> {
+    type Context<'a> = &'a mut Ctx;
+    type Return = Option<InstanceMethods>;
+
+    fn infer(self, ctx: Self::Context<'_>) -> Self::Return {
+        let InstanceDecl {
+            head,
+            constraints,
+            methods,
+            ..
+        } = self.data;
+
+        ctx.set_position(head.class.location);
+
+        let Some(class) = ctx.signatures.classes.get(&head.class.data).cloned() else {
+            ctx.error(format!("unbound class '{}'", head.class.data));
+            return None;
+        };
+
+        let mut set = HashSet::new();
+        ctx.free_variables(&head.typ, &mut set);
+
+        let instance_ctx = ctx.extend_types(set.iter());
+        let typ = instance_ctx.check_kind(&head.typ, &class.kind);
+
+        ctx.set_position(head.typ.location);
+
+        let (name, params) = match instance_head(&typ) {
+            Some(head) => head,
+            None if matches!(&*typ, MonoType::Error) => return None,
+            None => {
+                ctx.error(
+                    "the type of an instance must be a type constructor applied to distinct type variables"
+                        .to_string(),
+                );
+                return None;
+            }
+        };
+
+        if class.instances.contains_key(&name) {
+            ctx.error(format!(
+                "overlapping instances of '{}' for '{}'",
+                class.name, name
+            ));
+            return None;
+        }
+
+        let constraints = constraints
+            .iter()
+            .filter_map(|constraint| instance_ctx.constraint(constraint))
+            .collect();
+
+        let instance = Rc::new(InstanceSignature {
+            name: format!("{} {}", class.name, name),
+            symbol: format!("{}.{}", class.name, name),
+            params,
+            constraints,
+        });
+
+        let sig = ctx.signatures.classes.get_mut(&class.name).unwrap();
+        sig.instances.insert(name, instance.clone());
+
+        Some(InstanceMethods {
+            class,
+            instance,
+            typ,
+            typ_map: instance_ctx.typ_map,
+            location: head.class.location,
+            methods,
+        })
+    }
+}
+
+impl Infer for InstanceMethods {
+    type Context<'a> = &'a Ctx;
+    type Return = Vec<FnBody<Type>>;
+
+    fn infer(self, ctx: Self::Context<'_>) -> Self::Return {
+        let givens = self.instance.constraints.iter().cloned().map(Given::new);
+        let givens = givens.collect_vec();
+        let symbols = givens
+            .iter()
+            .map(|given| given.symbol.clone())
+            .collect_vec();
+
+        let instance_ctx = ctx.extend_kinded_types(self.typ_map).level_up();
+        let mut implemented = HashSet::new();
+        let mut bodies = Vec::with_capacity(self.methods.len());
+
+        for method in self.methods {
+            let mut ctx = instance_ctx.clone();
+            ctx.set_position(method.body.location);
+
+            let scheme = self
+                .class
+                .methods
+                .iter()
+                .find(|(name, _)| *name == method.name);
+
+            let Some((_, scheme)) = scheme else {
+                ctx.error(format!(
+                    "'{}' is not a method of the class '{}'",
+                    method.name, self.class.name
+                ));
+                continue;
+            };
+
+            if !implemented.insert(method.name.clone()) {
+                ctx.error(format!(
+                    "the method '{}' is implemented more than once",
+                    method.name
+                ));
+                continue;
+            }
+
+            if let Some(constraint) = method.constraints.first() {
+                ctx.set_position(constraint.class.location);
+                ctx.error("the constraints of an instance are declared on its head".to_string());
+            }
+
+            if method.effects.is_some() {
+                ctx.set_position(method.body.location);
+                ctx.error("the effects of a method are declared on its class".to_string());
+            }
+
+            // The other type variables of the method stay polymorphic.
+            let skolems = ctx.new_skolems(&scheme.names[1..]);
+            let types = iter::once(self.typ.clone()).chain(skolems).collect_vec();
+            let expected = scheme.instantiate_with(&types);
+
+            let location = method.body.location;
+
+            let body = match method.ret {
+                Some(ret) => Located::new(
+                    location,
+                    ExprKind::Annotation(Box::new(method.body), Box::new(ret)),
+                ),
+                None => method.body,
+            };
+
+            let abstraction = Located::new(
+                location,
+                ExprKind::Abstraction(method.params, Box::new(body)),
+            );
+
+            let mark = ctx.wanted_mark();
+            let body = abstraction.check(ctx.clone(), expected);
+            ctx.solve(mark, &givens, &[], true);
+
+            bodies.push(FnBody {
+                name: Symbol(format!("{}.{}", self.instance.symbol, method.name)),
+                dictionaries: symbols.clone(),
+                body,
+            });
+        }
+
+        let mut ctx = ctx.clone();
+        ctx.set_position(self.location);
+
+        for (name, _) in &self.class.methods {
+            if !implemented.contains(name) {
+                ctx.error(format!(
+                    "the method '{}' is missing in the instance '{}'",
+                    name, self.instance.name
+                ));
+            }
+        }
+
+        bodies
+    }
+}
+
+/// The type constructor and the type variables of the type of an instance.
+fn instance_head(typ: &Type) -> Option<(String, Vec<String>)> {
+    let MonoType::Application(name, args) = &*typ.clone().flatten() else {
+        return None;
+    };
+
+    let params = args
+        .iter()
+        .map(|arg| match &**arg {
+            MonoType::Var(name) => Some(name.clone()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    params
+        .iter()
+        .all_unique()
+        .then(|| (name.clone(), params.clone()))
+}
@@ -0,0 +1,290 @@
+//! Type inference for the declarations of effects and for the handlers.
+//!
+//! The operations of an effect are functions that perform it, so the expressions that apply them
+//! have the effect in their rows. A `handle` discharges the effect of the expression that it
+//! handles, its clauses receive the continuations of the operations.
+
+use super::Infer;
+use crate::{
+    context::{Ctx, InferError},
+    types::*,
+    unify::{subsume, unify},
+};
+
+use atiny_location::Located;
+use atiny_tree::{
+    elaborated::{self, Symbol},
+    r#abstract::*,
+};
+use itertools::Itertools;
+use std::{collections::HashSet, iter, rc::Rc};
+
+type Elaborated = elaborated::Expr<Type>;
+
+impl Ctx {
+    pub fn extend_effect_sigs(&mut self, effects: impl IntoIterator<Item = Located<EffectDecl>>) {
+        effects.into_iter().for_each(|effect| effect.infer(self));
+    }
+
+    /// Reports the effects that the `main` function performs, because there's nothing to handle
+    /// them.
+    pub fn unhandled_effects(&self, effects: &Type) {
+        for (_, effect) in effects.row().0 {
+            let effect = Names::new(&[&effect]).name(&effect);
+            self.error(format!(
+                "unhandled effect '{}' in main",
+                effect_label(&effect)
+            ));
+        }
+    }
+
+    /// Infers a `handle`. The expression can perform the effect of the operations of the clauses
+    /// besides the effects of the context, and the clauses are functions of the parameters of the
+    /// operations and of their continuations.
+    pub fn handle(&self, expr: &Expr, clauses: &[HandlerClause]) -> (Type, Elaborated) {
+        let mut ctx = self.clone();
+        let err_count = ctx.err_count();
+        let location = ctx.location;
+
+        let mut effect: Option<EffectSignature> = None;
+        let mut handled = Vec::new();
+        let mut operations = Vec::with_capacity(clauses.len());
+        let mut ret_clause = None;
+
+        for clause in clauses {
+            ctx.set_position(clause.name.location);
+            let name = &clause.name.data;
+
+            if handled.contains(&name) {
+                ctx.error(format!("the clause '{name}' is declared more than once"));
+                continue;
+            }
+
+            handled.push(name);
+
+            if name == "return" {
+                ret_clause = Some(clause);
+                continue;
+            }
+
+            let Some(DeclSignature::Operation(operation)) = ctx.signatures.values.get(name) else {
+                ctx.error(format!("'{name}' is not an operation"));
+                continue;
+            };
+
+            match &effect {
+                Some(effect) if effect.name != operation.effect => {
+                    ctx.error(format!(
+                        "the operation '{name}' is not of the effect '{}'",
+                        effect.name
+                    ));
+                    continue;
+                }
+                Some(_) => {}
+                None => effect = ctx.signatures.effects.get(&operation.effect).cloned(),
+            }
+
+            operations.push((clause, operation.clone()));
+        }
+
+        ctx.set_position(location);
+
+        let Some(effect) = effect else {
+            expr.infer(ctx.clone());
+            return ctx.new_error("the handler has no operations".to_string());
+        };
+
+        for (name, _) in &effect.operations {
+            if !handled.contains(&name) {
+                ctx.error(format!("the operation '{name}' is not handled"));
+            }
+        }
+
+        let args = effect.params.iter().map(|_| ctx.new_hole()).collect_vec();
+        let typ = MonoType::Application(effect.name.clone(), args.clone());
+        let outer = ctx.effects.clone();
+
+        let mut expr_ctx = ctx.clone();
+        let effects = vec![(effect.name.clone(), Rc::new(typ))];
+        expr_ctx.effects = Rc::new(MonoType::Effects(effects, Some(outer.clone())));
+
+        let (expr_ty, elab_expr) = expr.infer(expr_ctx);
+        let ret_ty = ctx.new_hole();
+        let mut elab_clauses = Vec::with_capacity(clauses.len());
+
+        match ret_clause {
+            Some(clause) => {
+                let expected = expr_ty.arrow_with(ret_ty.clone(), outer.clone());
+                let elab = ctx.handler_clause(clause, 1, expected);
+                elab_clauses.push((Symbol(clause.name.data.clone()), elab));
+            }
+            None => unify(ctx.clone(), expr_ty, ret_ty.clone()),
+        }
+
+        for (clause, operation) in operations {
+            let names = &operation.typ.names[args.len()..];
+            let holes = names.iter().map(|_| ctx.new_hole());
+            let types = args.iter().cloned().chain(holes).collect_vec();
+            let typ = operation.typ.instantiate_with(&types);
+
+            let params = typ.clone().iter().collect_vec();
+            let result = params.iter().fold(typ, |typ, _| match &*typ.flatten() {
+                MonoType::Arrow(_, to, _) => to.clone(),
+                _ => unreachable!("the operations are functions"),
+            });
+
+            let continuation = result.arrow_with(ret_ty.clone(), outer.clone());
+            let params = params.into_iter().chain(iter::once(continuation));
+            let expected = MonoType::rfold_arrow_with(params, ret_ty.clone(), outer.clone());
+
+            let arity = operation.typ.mono.clone().iter().count() + 1;
+            let elab = ctx.handler_clause(clause, arity, expected);
+            elab_clauses.push((Symbol(clause.name.data.clone()), elab));
+        }
+
+        let elaborated = if err_count == ctx.err_count() {
+            let effect = Symbol(effect.name);
+            Elaborated::Handle(Box::new(elab_expr), effect, elab_clauses)
+        } else {
+            Elaborated::Error
+        };
+
+        (ret_ty, elaborated)
+    }
+
+    /// Checks a clause of a handler against the type of its function.
+    fn handler_clause(&self, clause: &HandlerClause, arity: usize, expected: Type) -> Elaborated {
+        let mut ctx = self.clone();
+        ctx.set_position(clause.name.location);
+
+        if clause.params.len() != arity {
+            ctx.abstraction(&clause.params, &clause.body, None);
+            ctx.error(format!(
+                "the clause '{}' expects {} parameters, but got {}",
+                clause.name.data,
+                arity,
+                clause.params.len()
+            ));
+            return Elaborated::Error;
+        }
+
+        let (typ, elaborated) =
+            ctx.abstraction(&clause.params, &clause.body, Some(expected.clone()));
+        subsume(ctx, typ, expected);
+        elaborated
+    }
+}
+
+/// Declares an effect and its operations.
+///
+/// The kinds of the parameters of the effect are inferred by the types of the operations.
+impl Infer for Located<EffectDecl> {
+    type Context<'a> = &'a mut Ctx;
+    type Return = ();
+
+    fn infer(self, ctx: Self::Context<'_>) -> Self::Return {
+        let location = self.location;
+        let effect = self.data;
+
+        if ctx.signatures.effects.contains_key(&effect.name) {
+            ctx.set_position(location);
+            ctx.error(format!(
+                "the effect '{}' is declared more than once",
+                effect.name
+            ));
+            return;
+        }
+
+        let params = effect
+            .params
+            .iter()
+            .map(|param| (param.clone(), Kind::new_hole()))
+            .collect_vec();
+
+        let effect_ctx = ctx.extend_kinded_types(params.iter().cloned().collect());
+
+        let args = effect.params.iter().cloned().map(MonoType::var).collect();
+        let effects = vec![(
+            effect.name.clone(),
+            Rc::new(MonoType::Application(effect.name.clone(), args)),
+        )];
+        let effects = Rc::new(MonoType::Effects(effects, None));
+
+        let mut operations: Vec<(String, Rc<TypeScheme>)> = Vec::new();
+
+        for Field { name, ty, .. } in effect.operations {
+            ctx.set_position(ty.location);
+
+            let declared = ctx
+                .signatures
+                .effects
+                .values()
+                .flat_map(|effect| &effect.operations);
+
+            if declared
+                .chain(&operations)
+                .any(|(operation, _)| *operation == name)
+            {
+                ctx.error(format!("the operation '{name}' is declared more than once"));
+                continue;
+            }
+
+            // The other type variables of the operation are polymorphic too.
+            let mut set = HashSet::new();
+            effect_ctx.free_variables(&ty, &mut set);
+
+            let mono = ty.infer(effect_ctx.extend_types(set.iter()));
+
+            let Some(mono) = perform_last(&mono, &effects) else {
+                ctx.error(format!("the operation '{name}' must be a function"));
+                continue;
+            };
+
+            let names = effect
+                .params
+                .iter()
+                .cloned()
+                .chain(set.into_iter().sorted());
+            let scheme = TypeScheme::new(names.collect(), mono);
+
+            let sig = OperationSignature {
+                name: name.clone(),
+                effect: effect.name.clone(),
+                typ: scheme.clone(),
+            };
+
+            ctx.signatures
+                .values
+                .insert(name.clone(), DeclSignature::Operation(Rc::new(sig)));
+
+            operations.push((name, scheme));
+        }
+
+        for (_, kind) in &params {
+            kind.default_holes();
+        }
+
+        let sig = EffectSignature {
+            name: effect.name.clone(),
+            params,
+            operations,
+        };
+
+        ctx.signatures.effects.insert(effect.name, sig);
+    }
+}
+
+/// Replaces the effects of the last arrow of a function type, it's [None] if the type is not a
+/// function.
+fn perform_last(typ: &Type, effects: &Type) -> Option<Type> {
+    let MonoType::Arrow(from, to, _) = &*typ.clone().flatten() else {
+        return None;
+    };
+
+    let to = perform_last(to, effects).map_or_else(
+        || from.clone().arrow_with(to.clone(), effects.clone()),
+        |to| from.clone().arrow(to),
+    );
+
+    Some(to)
+}
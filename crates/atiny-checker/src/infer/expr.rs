@@ -4,15 +4,16 @@ use super::Infer;
 use crate::check::Check;
 use crate::context::{Ctx, InferError};
 use crate::exhaustive::Problem;
-use crate::types::{MonoType, Type, TypeScheme, TypeSignature};
+use crate::types::{MonoType, Names, Type, TypeScheme, TypeSignature};
 use crate::unify::unify;
 
 use atiny_error::SugestionKind;
+use atiny_location::Located;
 use atiny_tree::elaborated::{self, CaseTree, Stmt, Symbol, VariableNode};
 use atiny_tree::r#abstract::*;
 
 use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::rc::Rc;
 
 type Elaborated = elaborated::Expr<Type>;
@@ -28,39 +29,63 @@ impl Infer for &Expr {
 
         match &self.data {
             Atom(a) => match a {
-                Wildcard => ctx.new_error("`_` may only appear on patterns".to_string()),
+                Wildcard => ctx.typed_hole("_".to_string()),
 
                 Number(n) => (MonoType::typ("Int".to_string()), Elaborated::Number(*n)),
 
+                String(s) => (
+                    MonoType::typ("String".to_string()),
+                    Elaborated::String(s.clone()),
+                ),
+
+                Char(c) => (MonoType::typ("Char".to_string()), Elaborated::Char(*c)),
+
                 Tuple(vec) => {
                     let (typ, el) = vec.iter().map(|expr| expr.infer(ctx.clone())).unzip();
                     (MonoType::tuple(typ), Elaborated::Tuple(el))
                 }
 
-                Identifier(x) => match ctx.lookup(x) {
-                    Some(sigma) => {
-                        let (inst, inst_types) = sigma.instantiate(ctx);
+                Identifier(x) => ctx.lookup(x).map_or_else(
+                    || ctx.new_error(format!("unbound variable '{}'", x)),
+                    |sigma| {
+                        let (inst, inst_types) = sigma.instantiate(ctx.clone());
+                        let dictionaries = ctx.want(&sigma, &inst_types);
 
                         let variable_node = VariableNode {
                             inst_types,
                             name: Symbol(x.to_owned()),
+                            dictionaries,
                         };
 
                         (inst, Elaborated::Variable(variable_node))
-                    }
-
-                    None => ctx.new_error(format!("unbound variable '{}'", x)),
-                },
+                    },
+                ),
             },
 
             Application(fun, arg) => {
                 let (t0, mut elab_fun) = fun.infer(ctx.clone());
-                let (t1, elab_arg) = arg.infer(ctx.clone());
+                let t0 = t0.instantiate_forall(ctx.clone());
+
+                let (t_ret, elab_arg) = match &*t0.clone().flatten() {
+                    // A polymorphic argument can't be inferred, so it's checked against the type.
+                    MonoType::Arrow(param, ret, effects) if param.is_higher_rank() => {
+                        let elab_arg = arg.check(ctx.clone(), param.clone());
+                        ctx.perform(effects);
+                        (ret.clone().instantiate_forall(ctx), elab_arg)
+                    }
 
-                let t_ret = ctx.new_hole();
-                let function_type = t1.arrow(t_ret.clone());
+                    _ => {
+                        let (t1, elab_arg) = arg.infer(ctx.clone());
 
-                unify(ctx, t0, function_type);
+                        let t_ret = ctx.new_hole();
+                        let effects = ctx.new_effect_hole();
+                        let function_type = t1.arrow_with(t_ret.clone(), effects.clone());
+
+                        unify(ctx.clone(), t0, function_type);
+                        ctx.perform(&effects);
+                        (t_ret, elab_arg)
+                    }
+                };
 
                 let appl = match elab_fun {
                     Elaborated::Application(_, ref mut args, _) => {
@@ -73,94 +98,9 @@ impl Infer for &Expr {
                 (t_ret, appl)
             }
 
-            Abstraction(param, body) => {
-                let t = ctx.new_hole();
-                let new_ctx = ctx.extend(param.to_owned(), t.to_poly());
-                let (t_line, mut elab_body) = body.infer(new_ctx);
-
-                let symbol = Symbol(param.to_owned());
-
-                let abs = match elab_body {
-                    Elaborated::Abstraction(ref mut args, _) => {
-                        args.push_back(symbol);
-                        elab_body
-                    }
-                    _ => Elaborated::Abstraction([symbol].into(), Box::new(elab_body)),
-                };
-
-                (t.arrow(t_line), abs)
-            }
-
-            Match(e, clauses) => {
-                let err_count = ctx.err_count();
-
-                let (pat_ty, scrutinee) = e.infer(ctx.clone());
-                let ret_ty = ctx.new_hole();
-
-                let mut places = Vec::new();
-
-                for c in clauses {
-                    let mut set = HashSet::new();
-                    let pat_loc = c.pat.location;
+            Abstraction(params, body) => ctx.abstraction(params, body, None),
 
-                    let clause_pat = c.pat.clone().infer((&mut ctx, &mut set));
-                    ctx.set_position(pat_loc);
-
-                    unify(ctx.clone(), pat_ty.clone(), clause_pat);
-
-                    let (right, elaborated) = c.expr.infer(ctx.clone());
-
-                    unify(ctx.clone(), ret_ty.clone(), right);
-                    places.push(elaborated);
-                }
-
-                // We can't do coverage checking / exhaustiveness checking without a well typed
-                // pattern match and with linear variables.
-                let elaborated = if err_count == ctx.err_count() {
-                    let columns = if !clauses.is_empty() {
-                        let mut columns = vec![clauses[0].pat.clone()];
-
-                        for c in &clauses[1..] {
-                            let column_pat = c.pat.clone();
-                            let column = vec![column_pat.clone()];
-                            let problem = Problem::new(pat_ty.clone(), column, columns.clone());
-
-                            ctx.set_position(column_pat.location);
-                            let witness = problem.exhaustiveness(&ctx);
-
-                            if !witness.is_non_exhaustive() {
-                                ctx.error(format!("the clause is useless: {}", column_pat));
-                            }
-
-                            columns.push(column_pat.clone());
-                        }
-
-                        columns
-                    } else {
-                        Vec::new()
-                    };
-
-                    let problem = Problem::new(pat_ty, vec![wildcard()], columns);
-                    let witness = problem.exhaustiveness(&ctx);
-
-                    witness.result().map_or_else(
-                        |err| {
-                            let last_pat_loc = ctx.location;
-                            ctx.set_position(e.location);
-                            ctx.error(format!("non-exhaustive pattern match: {}", err));
-
-                            ctx.set_position(last_pat_loc);
-                            ctx.suggestion(format!("{} => _,", err), SugestionKind::Insert);
-                            Elaborated::Error
-                        },
-                        |tree| Elaborated::CaseTree(Box::new(scrutinee), CaseTree { tree, places }),
-                    )
-                } else {
-                    Elaborated::Error
-                };
-
-                (ret_ty, elaborated)
-            }
+            Match(e, clauses) => ctx.match_expr(e, clauses, None),
 
             Annotation(expr, typ) => {
                 let typ_res = typ.infer(ctx.clone());
@@ -194,8 +134,13 @@ impl Infer for &Expr {
                     let err_count = ctx.err_count();
                     let (expr_ty, elab_expr) = expr.infer(ctx.clone());
 
+                    if is_row_record(&expr_ty) {
+                        return ctx.row_update(expr_ty, elab_expr, user_fields);
+                    }
+
                     let Some((record, ret_type)) = ctx.as_record_info(&expr_ty) else {
-                        ctx.error(format!("the type '{expr_ty}' is not a record"));
+                        let shown = Names::new(&[&expr_ty]).name(&expr_ty);
+                        ctx.error(format!("the type '{shown}' is not a record"));
                         return (expr_ty, Elaborated::Error);
                     };
 
@@ -213,12 +158,40 @@ impl Infer for &Expr {
                 }
             },
 
+            Record(fields) => {
+                let mut types = Vec::with_capacity(fields.len());
+                let mut elab_fields = Vec::with_capacity(fields.len());
+
+                for ExprField { name, expr } in fields {
+                    let (typ, elab) = expr.infer(ctx.clone());
+
+                    if types.iter().any(|(other, _)| other == name) {
+                        ctx.set_position(expr.location);
+                        ctx.error(format!("field '{name}' is duplicated"));
+                        continue;
+                    }
+
+                    types.push((name.clone(), typ));
+                    elab_fields.push((Symbol(name.clone()), elab));
+                }
+
+                types.sort_by(|(l, _), (r, _)| l.cmp(r));
+
+                let typ = Rc::new(MonoType::Record(types, None));
+                (typ, Elaborated::Record(elab_fields))
+            }
+
             Field(expr, field) => {
                 let (expr_ty, elab_expr) = expr.infer(ctx.clone());
                 ctx.set_position(expr.location);
 
+                if is_row_record(&expr_ty) {
+                    return ctx.row_field(expr_ty, elab_expr, field);
+                }
+
                 let Some((record, ret_type)) = ctx.as_record_info(&expr_ty) else {
-                    return ctx.new_error(format!("the type '{expr_ty}' is not a record"));
+                    let shown = Names::new(&[&expr_ty]).name(&expr_ty);
+                    return ctx.new_error(format!("the type '{shown}' is not a record"));
                 };
 
                 let Some((_, field_cons)) = record.fields.iter().find(|(name, _)| name == field)
@@ -233,6 +206,7 @@ impl Infer for &Expr {
 
                 let field_ty = TypeScheme {
                     names: record.params.to_vec(),
+                    constraints: Vec::new(),
                     mono: field_cons.clone(),
                 }
                 .instantiate_with(&record.vars);
@@ -247,59 +221,46 @@ impl Infer for &Expr {
             }
 
             Block(statements) => statements.infer(ctx),
-        }
-    }
-}
 
-impl Infer for &[Statement] {
-    type Context<'a> = Ctx;
-    type Return = (Type, Elaborated);
+            Handle(expr, clauses) => ctx.handle(expr, clauses),
 
-    fn infer(self, mut ctx: Self::Context<'_>) -> Self::Return {
-        let mut elaborated = Vec::with_capacity(self.len());
-        let mut last = None;
+            Ref(expr) => {
+                let (typ, elab) = expr.infer(ctx);
+                (MonoType::reference(typ), Elaborated::Ref(Box::new(elab)))
+            }
 
-        for stmt in self.iter() {
-            match &stmt.data {
-                StatementKind::Let(pat, exp) => {
-                    let (typ, elab) = exp.infer(ctx.level_up());
-                    let witness = ctx.single_exhaustiveness(pat, typ);
+            Deref(expr) => {
+                let typ = ctx.new_hole();
+                let elab = expr.check(ctx, MonoType::reference(typ.clone()));
+                (typ, Elaborated::Deref(Box::new(elab)))
+            }
 
-                    match witness.result() {
-                        Err(err) => {
-                            ctx.set_position(pat.location);
-                            ctx.error("refutable pattern in let statement. Consider using `match` instead".to_string());
+            Assign(reference, expr) => {
+                let typ = ctx.new_hole();
+                let elab_ref = reference.check(ctx.clone(), MonoType::reference(typ.clone()));
+                let elab = expr.check(ctx, typ);
 
-                            ctx.set_position(stmt.location);
-                            ctx.suggestions(
-                                vec![
-                                    format!("match {exp} {{"),
-                                    format!("    {pat} => _,"),
-                                    format!("    {err} => _,"),
-                                ],
-                                SugestionKind::Replace,
-                            );
-                        }
+                (
+                    MonoType::typ("()".to_string()),
+                    Elaborated::Assign(Box::new(elab_ref), Box::new(elab)),
+                )
+            }
 
-                        Ok(tree) => elaborated.push(Stmt::Let(tree, elab)),
-                    }
+            Hole(name) => ctx.typed_hole(format!("?{name}")),
 
-                    last = None;
-                }
+            Operators(..) => unreachable!("operator sequences are resolved by the parser"),
 
-                StatementKind::Expr(expr) => {
-                    let (typ, elab) = expr.infer(ctx.clone());
-                    elaborated.push(Stmt::Expr(elab));
-                    last = Some(typ);
-                }
-            }
+            Error => (Rc::new(MonoType::Error), Elaborated::Error),
         }
+    }
+}
 
-        let Some(ret) = last else {
-            todo!("report that let statements cant be at the end of a block");
-        };
+impl Infer for &[Statement] {
+    type Context<'a> = Ctx;
+    type Return = (Type, Elaborated);
 
-        (ret, Elaborated::Block(elaborated))
+    fn infer(self, ctx: Self::Context<'_>) -> Self::Return {
+        ctx.block(self, None)
     }
 }
 
@@ -322,28 +283,26 @@ impl Infer for &[ExprField] {
         let mut elab_fields = vec![];
 
         for ExprField { name, expr } in self {
-            let (field_ty, field_expr) = expr.infer(ctx.clone());
-
-            if fields_map.contains_key(name) {
-                if fields_to_remove.contains(&name) {
-                    ctx.set_position(expr.location);
-
-                    fields_to_remove.remove(&name);
+            // The fields of the record are checked against their types.
+            if fields_to_remove.remove(&name) {
+                ctx.set_position(expr.location);
 
-                    let field = fields_map[name];
+                let field = TypeScheme {
+                    names: record.params.to_vec(),
+                    constraints: Vec::new(),
+                    mono: fields_map[name].clone(),
+                }
+                .instantiate_with(&record.vars);
 
-                    let field = TypeScheme {
-                        names: record.params.to_vec(),
-                        mono: field.clone(),
-                    }
-                    .instantiate_with(&record.vars);
+                let field_expr = expr.check(ctx.clone(), field);
+                elab_fields.push((Symbol(name.to_owned()), field_expr));
+                continue;
+            }
 
-                    unify(ctx.clone(), field_ty, field.clone());
+            expr.infer(ctx.clone());
 
-                    elab_fields.push((Symbol(name.to_owned()), field_expr));
-                } else {
-                    ctx.error(format!("field '{name}' is duplicated"));
-                }
+            if fields_map.contains_key(name) {
+                ctx.error(format!("field '{name}' is duplicated"));
             } else {
                 ctx.error(format!(
                     "field '{name}' does not exist in type '{}'",
@@ -370,7 +329,535 @@ pub struct RecordInfo<'a> {
     vars: Vec<Type>,
 }
 
+/// If the fields of a type are known by its row instead of a declared record.
+fn is_row_record(typ: &Type) -> bool {
+    matches!(
+        &*typ.clone().flatten(),
+        MonoType::Record(..) | MonoType::Hole(_)
+    )
+}
+
+/// The alternatives of an or-pattern or the pattern itself if it's not one.
+fn alternatives(pat: &Pattern) -> Vec<&Pattern> {
+    match &pat.data {
+        PatternKind::Or(left, right) => {
+            let mut vec = alternatives(left);
+            vec.extend(alternatives(right));
+            vec
+        }
+        _ => vec![pat],
+    }
+}
+
 impl Ctx {
+    /// Infers the type of a pattern match, or checks every clause against the expected type.
+    pub fn match_expr(
+        &self,
+        e: &Expr,
+        clauses: &[Clause],
+        expected: Option<Type>,
+    ) -> (Type, Elaborated) {
+        let mut ctx = self.clone();
+        let err_count = ctx.err_count();
+
+        let (pat_ty, scrutinee) = e.infer(ctx.clone());
+        let ret_ty = expected.clone().unwrap_or_else(|| ctx.new_hole());
+
+        let mut places = Vec::new();
+        let mut guards = Vec::new();
+
+        for c in clauses {
+            let mut set = HashSet::new();
+            let pat_loc = c.pat.location;
+
+            let clause_pat = c.pat.clone().infer((&mut ctx, &mut set));
+            ctx.set_position(pat_loc);
+
+            unify(ctx.clone(), pat_ty.clone(), clause_pat);
+
+            let bool = MonoType::typ("Bool".to_string());
+            guards.push(c.guard.as_ref().map(|guard| guard.check(ctx.clone(), bool)));
+
+            let elaborated = if expected.is_some() {
+                c.expr.check(ctx.clone(), ret_ty.clone())
+            } else {
+                let (right, elaborated) = c.expr.infer(ctx.clone());
+                unify(ctx.clone(), ret_ty.clone(), right);
+                elaborated
+            };
+
+            places.push(elaborated);
+        }
+
+        // We can't do coverage checking / exhaustiveness checking without a well typed
+        // pattern match, with linear variables or with clauses that could not be parsed.
+        let recovered = clauses.iter().any(|c| c.pat.data.is_error());
+
+        let elaborated = if err_count == ctx.err_count() && !recovered {
+            let mut columns = Vec::new();
+
+            for c in clauses {
+                // Guarded clauses don't cover the values matched by their patterns.
+                let guarded = c.guard.is_some();
+
+                if !ctx.is_useful(&pat_ty, &columns, &c.pat) {
+                    ctx.set_position(c.pat.location);
+                    ctx.error(format!("the clause is useless: {}", c.pat));
+                } else if let PatternKind::Or(..) = c.pat.data {
+                    let mut previous = columns.clone();
+
+                    for alternative in alternatives(&c.pat) {
+                        if !ctx.is_useful(&pat_ty, &previous, alternative) {
+                            ctx.set_position(alternative.location);
+                            ctx.error(format!("the pattern is useless: {}", alternative));
+                        }
+
+                        previous.push((alternative.clone(), guarded));
+                    }
+                }
+
+                ctx.set_position(c.pat.location);
+                columns.push((c.pat.clone(), guarded));
+            }
+
+            let problem = Problem::with_guards(pat_ty, vec![wildcard()], columns);
+            let witness = problem.exhaustiveness(&ctx);
+
+            witness.result().map_or_else(
+                |err| {
+                    let last_pat_loc = ctx.location;
+                    ctx.set_position(e.location);
+                    ctx.error(format!("non-exhaustive pattern match: {}", err));
+
+                    ctx.set_position(last_pat_loc);
+                    ctx.suggestion(format!("{} => _,", err), SugestionKind::Insert);
+                    Elaborated::Error
+                },
+                |tree| {
+                    Elaborated::CaseTree(
+                        Box::new(scrutinee),
+                        CaseTree {
+                            tree,
+                            places,
+                            guards,
+                        },
+                    )
+                },
+            )
+        } else {
+            Elaborated::Error
+        };
+
+        (ret_ty, elaborated)
+    }
+
+    /// If an expression is a syntactic value, so evaluating it can't perform effects. The values
+    /// are variables, literals, functions, and constructors, tuples and records of values.
+    pub fn is_value(&self, expr: &Expr) -> bool {
+        match &expr.data {
+            ExprKind::Atom(AtomKind::Tuple(exprs)) => exprs.iter().all(|expr| self.is_value(expr)),
+            ExprKind::Atom(AtomKind::Wildcard) => false,
+            ExprKind::Atom(_) | ExprKind::Abstraction(..) => true,
+            ExprKind::Annotation(expr, _) => self.is_value(expr),
+            ExprKind::Application(fun, arg) => self.is_constructor(fun) && self.is_value(arg),
+            ExprKind::RecordCreation(expr, fields) => {
+                self.is_value(expr) && fields.iter().all(|field| self.is_value(&field.expr))
+            }
+            ExprKind::Record(fields) => fields.iter().all(|field| self.is_value(&field.expr)),
+            _ => false,
+        }
+    }
+
+    /// If an expression is a constructor applied to values.
+    fn is_constructor(&self, expr: &Expr) -> bool {
+        match &expr.data {
+            ExprKind::Atom(AtomKind::Identifier(name)) => self.lookup_cons(name).is_some(),
+            ExprKind::Application(fun, arg) => self.is_constructor(fun) && self.is_value(arg),
+            _ => false,
+        }
+    }
+
+    /// Infers the type of a block, or checks its last expression against the expected type.
+    pub fn block(&self, statements: &[Statement], expected: Option<Type>) -> (Type, Elaborated) {
+        let mut ctx = self.clone();
+        let mut elaborated = Vec::with_capacity(statements.len());
+        let mut last = None;
+
+        for (index, stmt) in statements.iter().enumerate() {
+            match &stmt.data {
+                // The bindings are generalized if the value is a syntactic value, otherwise only
+                // the holes in covariant positions of their types are, by the relaxed value
+                // restriction.
+                StatementKind::Let(pat, exp) => {
+                    let mark = ctx.wanted_mark();
+                    let mut let_ctx = ctx.level_up();
+                    let (typ, mut elab) = exp.infer(let_ctx.clone());
+                    let (witness, names) = let_ctx.bind_pattern(pat, typ);
+
+                    let bindings = names
+                        .into_iter()
+                        .sorted()
+                        .filter_map(|name| {
+                            let typ = let_ctx.lookup(&name)?.mono.clone();
+                            Some((name, typ))
+                        })
+                        .collect_vec();
+
+                    let types = bindings.iter().map(|(_, typ)| typ.clone()).collect_vec();
+                    MonoType::close_effects(&types, &ctx);
+
+                    let is_value = ctx.is_value(exp);
+                    let is_variable = matches!(
+                        &pat.data,
+                        PatternKind::Atom(AtomKind::Identifier(name)) if ctx.lookup_cons(name).is_none()
+                    );
+
+                    // Only a variable can receive the dictionaries of the constraints, so the
+                    // holes with constraints of the other bindings stay monomorphic. It's said
+                    // when they would be generalized otherwise.
+                    if !(is_value && is_variable) {
+                        let constrained = ctx.wanted_holes(mark);
+
+                        if is_value {
+                            ctx.set_position(pat.location);
+
+                            for (name, typ) in &bindings {
+                                if constrained.iter().any(|hole| typ.contains_hole(hole)) {
+                                    ctx.info(format!("'{name}' is not generalized because only a variable can receive the dictionaries of its constraints"));
+                                }
+                            }
+                        }
+
+                        MonoType::lower_holes(&constrained, &ctx);
+                    }
+
+                    if !is_value {
+                        let holes = MonoType::noncovariant_holes(&types, &ctx);
+                        MonoType::lower_holes(&holes, &ctx);
+                    }
+
+                    let generalized = if is_value && is_variable {
+                        &types[..]
+                    } else {
+                        &[]
+                    };
+                    let givens = ctx.solve(mark, &[], generalized, false);
+                    let predicates = givens.iter().map(|given| given.predicate.clone());
+                    let predicates = predicates.collect_vec();
+
+                    if !givens.is_empty() {
+                        let symbols = givens.into_iter().map(|given| given.symbol).collect_vec();
+                        elab = Elaborated::Abstraction(symbols.into(), Box::new(elab));
+                    }
+
+                    for (name, typ) in bindings {
+                        let scheme = typ.generalize_qualified(ctx.clone(), &predicates);
+                        ctx = ctx.extend(name, scheme);
+                    }
+
+                    match witness.result() {
+                        Err(err) => {
+                            ctx.set_position(pat.location);
+                            ctx.error("refutable pattern in let statement. Consider using `match` instead".to_string());
+
+                            ctx.set_position(stmt.location);
+                            ctx.suggestions(
+                                vec![
+                                    format!("match {exp} {{"),
+                                    format!("    {pat} => _,"),
+                                    format!("    {err} => _,"),
+                                ],
+                                SugestionKind::Replace,
+                            );
+                        }
+
+                        Ok(tree) => elaborated.push(Stmt::Let(tree, elab)),
+                    }
+
+                    last = None;
+                }
+
+                // The bindings are monomorphic inside of their own values and they are only
+                // generalized for the rest of the block.
+                StatementKind::LetRec(bindings) => {
+                    let rec_ctx = ctx.level_up();
+                    let mut group_ctx = rec_ctx.clone();
+                    let mut names = HashSet::new();
+                    let mut holes = Vec::with_capacity(bindings.len());
+
+                    for RecBinding { name, .. } in bindings {
+                        if !names.insert(&name.data) {
+                            ctx.set_position(name.location);
+                            ctx.error(format!("identifier '{}' bound more than once", name.data));
+                        }
+
+                        let hole = rec_ctx.new_hole();
+                        group_ctx = group_ctx.extend(name.data.clone(), hole.to_poly());
+                        holes.push(hole);
+                    }
+
+                    let mark = ctx.wanted_mark();
+                    let mut values = Vec::with_capacity(bindings.len());
+
+                    for (RecBinding { name, value }, hole) in bindings.iter().zip(&holes) {
+                        if !matches!(value.data, ExprKind::Abstraction(..)) {
+                            ctx.set_position(value.location);
+                            ctx.error("only functions can be defined recursively".to_string());
+                        }
+
+                        let err_count = ctx.err_count();
+                        let (typ, elab) = value.infer(group_ctx.clone());
+
+                        // A value with errors would only repeat them in the uses of its binding.
+                        if err_count == ctx.err_count() {
+                            group_ctx.set_position(value.location);
+                            unify(group_ctx.clone(), hole.clone(), typ);
+                        }
+
+                        values.push((Symbol(name.data.clone()), elab));
+                    }
+
+                    // The bindings receive the dictionaries of the constraints that are generalized.
+                    let givens = ctx.solve(mark, &[], &holes, false);
+                    MonoType::close_effects(&holes, &ctx);
+                    let predicates = givens.iter().map(|given| given.predicate.clone());
+                    let predicates = predicates.collect_vec();
+
+                    if !givens.is_empty() {
+                        let symbols = givens.into_iter().map(|given| given.symbol).collect_vec();
+
+                        for (_, value) in &mut values {
+                            let body = std::mem::replace(value, Elaborated::Error);
+                            *value =
+                                Elaborated::Abstraction(symbols.clone().into(), Box::new(body));
+                        }
+                    }
+
+                    for (RecBinding { name, .. }, hole) in bindings.iter().zip(holes) {
+                        let scheme = hole.generalize_qualified(ctx.clone(), &predicates);
+                        ctx = ctx.extend(name.data.clone(), scheme);
+                    }
+
+                    elaborated.push(Stmt::LetRec(values));
+                    last = None;
+                }
+
+                // The last expression is the value of the block, the other ones are only
+                // sequenced so they must be the unit.
+                StatementKind::Expr(expr) => {
+                    let (typ, elab) = match &expected {
+                        _ if index + 1 < statements.len() => {
+                            let unit = MonoType::typ("()".to_string());
+                            (unit.clone(), expr.check(ctx.clone(), unit))
+                        }
+                        Some(expected) => {
+                            (expected.clone(), expr.check(ctx.clone(), expected.clone()))
+                        }
+                        None => expr.infer(ctx.clone()),
+                    };
+
+                    elaborated.push(Stmt::Expr(elab));
+                    last = Some(typ);
+                }
+            }
+        }
+
+        // A block that ends in a definition is the unit, like an empty one.
+        let ret = last.unwrap_or_else(|| {
+            let location = statements.last().map_or(ctx.location, |stmt| stmt.location);
+            let unit = Located::new(location, ExprKind::Atom(AtomKind::unit()));
+
+            let typ = expected.unwrap_or_else(|| ctx.new_hole());
+            elaborated.push(Stmt::Expr(unit.check(ctx.clone(), typ.clone())));
+            typ
+        });
+
+        (ret, Elaborated::Block(elaborated))
+    }
+
+    /// Infers the type of a function. If it's expected to be an arrow, its parameters take the
+    /// types of the arrow, so they can be polymorphic, and the body is checked against the rest.
+    /// The body can perform the effects of the last arrow.
+    pub fn abstraction(
+        &self,
+        params: &[Param],
+        body: &Expr,
+        expected: Option<Type>,
+    ) -> (Type, Elaborated) {
+        let mut new_ctx = self.clone();
+        let mut expected = expected;
+        let mut latent = None;
+        let mut types = Vec::with_capacity(params.len());
+        let mut symbols = VecDeque::with_capacity(params.len());
+        let mut statements = Vec::new();
+        let mut refutable = false;
+
+        for (index, Param { pat, typ }) in params.iter().enumerate() {
+            let arrow = expected.take().and_then(|typ| match &*typ.flatten() {
+                MonoType::Arrow(from, to, effects) => {
+                    Some((from.clone(), to.clone(), effects.clone()))
+                }
+                _ => None,
+            });
+
+            let t = match (typ, &arrow) {
+                (Some(typ), _) => typ.infer(self.clone()),
+                (None, Some((from, ..))) => from.clone(),
+                (None, None) => self.new_hole(),
+            };
+
+            latent = arrow.as_ref().map(|(.., effects)| effects.clone());
+            expected = arrow.map(|(_, to, _)| to);
+
+            let witness = new_ctx.single_exhaustiveness(pat, t.clone());
+
+            match (&pat.data, witness.result()) {
+                (_, Err(err)) => {
+                    new_ctx.set_position(pat.location);
+                    new_ctx.error(format!(
+                        "refutable pattern in lambda argument. pattern `{}` not covered",
+                        err
+                    ));
+                    refutable = true;
+                }
+
+                (PatternKind::Atom(AtomKind::Identifier(x)), _)
+                    if self.lookup_cons(x).is_none() =>
+                {
+                    symbols.push_back(Symbol(x.to_owned()));
+                }
+
+                // Every other pattern receives a name that can't be written by the user
+                // and is destructed by a let statement in the beginning of the body.
+                (_, Ok(tree)) => {
+                    let name = format!("#{index}");
+
+                    let variable_node = VariableNode {
+                        inst_types: Vec::new(),
+                        name: Symbol(name.clone()),
+                        dictionaries: Vec::new(),
+                    };
+
+                    symbols.push_back(Symbol(name));
+                    statements.push(Stmt::Let(tree, Elaborated::Variable(variable_node)));
+                }
+            }
+
+            types.push(t);
+        }
+
+        let effects = latent.unwrap_or_else(|| self.new_effect_hole());
+        new_ctx.effects = effects.clone();
+
+        let (t_line, elab_body) = match expected {
+            Some(expected) => (expected.clone(), body.check(new_ctx, expected)),
+            None => body.infer(new_ctx),
+        };
+
+        let elab_body = if statements.is_empty() {
+            elab_body
+        } else {
+            statements.push(Stmt::Expr(elab_body));
+            Elaborated::Block(statements)
+        };
+
+        let abs = match elab_body {
+            _ if refutable => Elaborated::Error,
+            Elaborated::Abstraction(args, body) => {
+                symbols.extend(args);
+                Elaborated::Abstraction(symbols, body)
+            }
+            _ => Elaborated::Abstraction(symbols, Box::new(elab_body)),
+        };
+
+        let typ = MonoType::rfold_arrow_with(types.into_iter(), t_line, effects);
+        (typ, abs)
+    }
+
+    /// Performs the effects of a row where the expression is, so they must be in the effects of
+    /// the context.
+    pub fn perform(&self, effects: &Type) {
+        unify(self.clone(), self.effects.clone(), effects.open(self));
+    }
+
+    /// Checks if a pattern matches some value that is not matched by the previous ones.
+    fn is_useful(&self, typ: &Type, previous: &[(Pattern, bool)], pat: &Pattern) -> bool {
+        let problem = Problem::with_guards(typ.clone(), vec![pat.clone()], previous.to_vec());
+        problem.exhaustiveness(self).is_non_exhaustive()
+    }
+
+    /// Infers the field of a record that is known by its row, so the record can have any other
+    /// fields.
+    fn row_field(&self, expr_ty: Type, elab_expr: Elaborated, field: &str) -> (Type, Elaborated) {
+        let err_count = self.err_count();
+        let field_ty = self.new_hole();
+
+        let fields = vec![(field.to_string(), field_ty.clone())];
+        let row = Rc::new(MonoType::Record(fields, Some(self.new_hole())));
+        unify(self.clone(), expr_ty.clone(), row);
+
+        if err_count != self.err_count() {
+            return (Rc::new(MonoType::Error), Elaborated::Error);
+        }
+
+        let symbol = Symbol(field.to_string());
+        let elaborated = Elaborated::RowField(Box::new(elab_expr), symbol, expr_ty);
+        (field_ty, elaborated)
+    }
+
+    /// Infers the update of a record that is known by its row, the fields keep their types.
+    fn row_update(
+        &self,
+        expr_ty: Type,
+        elab_expr: Elaborated,
+        fields: &[ExprField],
+    ) -> (Type, Elaborated) {
+        let mut ctx = self.clone();
+        let err_count = ctx.err_count();
+        let mut updated: Vec<(&ExprField, Type)> = Vec::with_capacity(fields.len());
+
+        for field in fields {
+            if updated.iter().any(|(other, _)| other.name == field.name) {
+                field.expr.infer(ctx.clone());
+                ctx.set_position(field.expr.location);
+                ctx.error(format!("field '{}' is duplicated", field.name));
+                continue;
+            }
+
+            updated.push((field, ctx.new_hole()));
+        }
+
+        let types = updated
+            .iter()
+            .map(|(field, typ)| (field.name.clone(), typ.clone()));
+        let types = types.sorted_by(|(l, _), (r, _)| l.cmp(r)).collect();
+
+        let row = Rc::new(MonoType::Record(types, Some(ctx.new_hole())));
+        unify(ctx.clone(), expr_ty.clone(), row);
+
+        if err_count != ctx.err_count() {
+            return (expr_ty, Elaborated::Error);
+        }
+
+        let elab_fields = updated
+            .into_iter()
+            .map(|(field, typ)| {
+                (
+                    Symbol(field.name.clone()),
+                    field.expr.check(ctx.clone(), typ),
+                )
+            })
+            .collect();
+
+        let elaborated = if err_count != ctx.err_count() {
+            Elaborated::Error
+        } else {
+            Elaborated::RecordUpdate(Box::new(elab_expr), elab_fields)
+        };
+
+        (expr_ty, elaborated)
+    }
+
     fn as_record_info(&self, expr_ty: &Type) -> Option<(RecordInfo<'_>, Type)> {
         expr_ty.get_constructor().and_then(|name| {
             self.lookup_type(&name)
@@ -386,6 +873,7 @@ impl Ctx {
         sig.get_product().map(|fields| {
             let (ret_type, vars) = TypeScheme {
                 names: sig.params.clone(),
+                constraints: Vec::new(),
                 mono: sig.application(),
             }
             .instantiate(self.clone());
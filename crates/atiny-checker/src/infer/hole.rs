@@ -0,0 +1,95 @@
+//! Type inference for the typed holes, like `?todo`.
+//!
+//! A hole can have any type, so it doesn't fail the compilation. Its type is reported once the
+//! types around it are known, together with the bindings in scope that could fill it.
+
+use crate::{context::Ctx, types::*, unify::unify};
+
+use atiny_tree::elaborated;
+use std::{
+    fmt::{self, Display},
+    iter,
+    rc::Rc,
+};
+
+type Elaborated = elaborated::Expr<Type>;
+
+impl Ctx {
+    /// Infers a typed hole, it's elaborated into an error because there's nothing to run.
+    pub fn typed_hole(&self, name: String) -> (Type, Elaborated) {
+        let typ = self.new_hole();
+
+        let locals = self
+            .map
+            .iter()
+            .map(|(name, scheme)| (name.clone(), scheme.clone()));
+        let globals = self
+            .signatures
+            .values
+            .keys()
+            .filter(|name| !self.map.contains_key(*name))
+            .filter_map(|name| Some((name.clone(), self.lookup(name)?)));
+
+        // The candidates are unified by a context of their own, so their errors are not reported.
+        let mut ctx = self.clone();
+        ctx.errors = Default::default();
+
+        self.info(TypedHole {
+            name,
+            typ: typ.clone(),
+            candidates: locals.chain(globals).collect(),
+            ctx,
+        });
+
+        (typ, Elaborated::Error)
+    }
+}
+
+/// The report of a typed hole, it's only shown after the type of the hole is inferred.
+struct TypedHole {
+    name: String,
+    typ: Type,
+    candidates: Vec<(String, Rc<TypeScheme>)>,
+    ctx: Ctx,
+}
+
+impl TypedHole {
+    /// If a binding has a type that unifies with the type of the hole. Both types are copied, so
+    /// their holes are not filled.
+    fn fits(&self, scheme: &TypeScheme) -> bool {
+        let mut ctx = self.ctx.clone();
+        ctx.errors = Default::default();
+
+        let (typ, _) = scheme.instantiate(ctx.clone());
+
+        let mut holes = Vec::new();
+        let typ = typ.freshen(&ctx, &mut holes);
+        let expected = self.typ.freshen(&ctx, &mut holes);
+
+        unify(ctx.clone(), expected, typ);
+        ctx.err_count() == 0
+    }
+}
+
+impl Display for TypedHole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let types =
+            iter::once(&self.typ).chain(self.candidates.iter().map(|(_, scheme)| &scheme.mono));
+        let mut names = Names::new(&types.collect::<Vec<_>>());
+        let typ = names.name(&self.typ);
+
+        write!(f, "found the hole '{}' of type '{typ}'", self.name)?;
+
+        let mut fits = self
+            .candidates
+            .iter()
+            .filter(|(_, scheme)| self.fits(scheme))
+            .peekable();
+
+        if fits.peek().is_some() {
+            write!(f, "\nthe bindings in scope that fit it:")?;
+        }
+
+        fits.try_for_each(|(name, scheme)| write!(f, "\n  {name} : {}", names.scheme(scheme)))
+    }
+}
@@ -1,7 +1,10 @@
 //! Exposes an interface to infer the type of expressions, patterns, types and some constructions.
 //! The main construction of this module is the [Infer] trait.
 
+pub mod class;
+pub mod effect;
 pub mod expr;
+pub mod hole;
 pub mod pat;
 pub mod top_level;
 pub mod typ;
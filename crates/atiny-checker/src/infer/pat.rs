@@ -1,10 +1,11 @@
 use super::Infer;
 use crate::context::{Ctx, InferError};
 use crate::exhaustive::{Problem, Witness};
-use crate::types::{MonoType, Type};
+use crate::types::{MonoType, Type, TypeScheme};
 use crate::unify::unify;
 
-use atiny_tree::r#abstract::{wildcard, AtomKind, Pattern, PatternKind};
+use atiny_tree::r#abstract::{wildcard, AtomKind, Pattern, PatternField, PatternKind};
+use itertools::Itertools;
 use std::{collections::HashSet, rc::Rc};
 
 impl Infer for Pattern {
@@ -21,6 +22,10 @@ impl Infer for Pattern {
 
                 Number(_) => MonoType::typ("Int".to_string()),
 
+                String(_) => MonoType::typ("String".to_string()),
+
+                Char(_) => MonoType::typ("Char".to_string()),
+
                 Identifier(x) if ctx.lookup_cons(&x).is_some() => {
                     Self::new(self.location, PatternKind::Constructor(x, vec![])).infer((ctx, set))
                 }
@@ -57,7 +62,7 @@ impl Infer for Pattern {
                 for pat in args {
                     let pat_ty = pat.infer((ctx, set));
 
-                    let MonoType::Arrow(left, right) = &*typ else {
+                    let MonoType::Arrow(left, right, _) = &*typ else {
                         unreachable!("impossible branch when matching constructor arguments")
                     };
 
@@ -67,39 +72,131 @@ impl Infer for Pattern {
 
                 typ
             }
-        }
-    }
-}
 
-impl Ctx {
-    pub fn single_exhaustiveness(&mut self, pattern: &Pattern, pattern_type: Type) -> Witness {
-        let mut set = HashSet::new();
-        let cons_pat = pattern.clone().infer((self, &mut set));
+            PatternKind::Or(left, right) => {
+                let mut right_ctx = ctx.clone();
+                let mut right_set = set.clone();
 
-        unify(self.clone(), cons_pat.clone(), pattern_type.clone());
-        self.extend_with_pattern(pattern, pattern_type);
+                let left_ty = left.infer((ctx, set));
+                let right_ty = right.infer((&mut right_ctx, &mut right_set));
 
-        let problem = Problem::new(cons_pat, vec![wildcard()], vec![pattern.clone()]);
-        problem.exhaustiveness(self)
-    }
+                ctx.set_position(self.location);
+                unify(ctx.clone(), left_ty.clone(), right_ty);
+
+                let mut unbound = set.symmetric_difference(&right_set).collect_vec();
+                unbound.sort();
 
-    pub fn extend_with_pattern(&mut self, pattern: &Pattern, pattern_type: Type) {
-        match (&pattern.data, &*pattern_type) {
-            (PatternKind::Atom(AtomKind::Wildcard), _) => {}
+                for name in unbound {
+                    ctx.error(format!("variable '{name}' is not bound in all patterns"));
+                }
 
-            (PatternKind::Atom(atom), _) => {
-                self.map.insert(atom.to_string(), pattern_type.to_poly());
+                for name in set.intersection(&right_set) {
+                    let (Some(left), Some(right)) = (ctx.lookup(name), right_ctx.lookup(name))
+                    else {
+                        continue;
+                    };
+
+                    unify(ctx.clone(), left.mono.clone(), right.mono.clone());
+                }
+
+                left_ty
             }
 
-            (PatternKind::Constructor(_, patterns), MonoType::Application(_, apps)) => {
-                for (arg, app) in patterns.iter().zip(apps.iter()) {
-                    self.extend_with_pattern(arg, app.clone());
+            PatternKind::As(name, pat) => {
+                let typ = pat.infer((ctx, set));
+                ctx.set_position(self.location);
+
+                if set.insert(name.clone()) {
+                    *ctx = ctx.extend(name, typ.to_poly());
+                    typ
+                } else {
+                    ctx.new_error(format!("identifier '{}' bound more than once", name))
                 }
             }
 
-            (_, MonoType::Error) => {}
+            PatternKind::Record(name, fields, rest) => {
+                let Some(sig) = ctx.lookup_type(&name).cloned() else {
+                    return ctx.new_error(format!("unbound record '{}'", name));
+                };
+
+                let Some(record) = sig.get_product() else {
+                    return ctx.new_error(format!("the type '{}' is not a record", name));
+                };
+
+                let scheme = TypeScheme {
+                    names: sig.params.clone(),
+                    constraints: Vec::new(),
+                    mono: sig.application(),
+                };
+
+                let (typ, vars) = scheme.instantiate(ctx.clone());
+                let mut written = HashSet::new();
+
+                for PatternField { name: field, pat } in fields {
+                    let field_location = pat.location;
+                    let pat_ty = pat.infer((ctx, set));
+                    ctx.set_position(field_location);
+
+                    let Some((_, field_ty)) = record.iter().find(|(n, _)| *n == field) else {
+                        ctx.error(format!("field '{field}' does not exist in type '{name}'"));
+                        continue;
+                    };
+
+                    if !written.insert(field.clone()) {
+                        ctx.error(format!("field '{field}' is duplicated"));
+                        continue;
+                    }
+
+                    let field_ty = TypeScheme {
+                        names: sig.params.clone(),
+                        constraints: Vec::new(),
+                        mono: field_ty.clone(),
+                    }
+                    .instantiate_with(&vars);
+
+                    unify(ctx.clone(), pat_ty, field_ty);
+                }
+
+                let missing = record
+                    .iter()
+                    .filter(|(field, _)| !written.contains(field))
+                    .map(|(field, _)| field)
+                    .collect_vec();
+
+                if !rest && !missing.is_empty() {
+                    ctx.set_position(self.location);
+                    ctx.error(format!(
+                        "fields {} are missing, use `..` to ignore them",
+                        missing.iter().join(", ")
+                    ));
+                }
+
+                typ
+            }
 
-            (_, _) => unreachable!(),
+            PatternKind::Error => Rc::new(MonoType::Error),
         }
     }
 }
+
+impl Ctx {
+    pub fn single_exhaustiveness(&mut self, pattern: &Pattern, pattern_type: Type) -> Witness {
+        self.bind_pattern(pattern, pattern_type).0
+    }
+
+    /// Like [Self::single_exhaustiveness], but it also returns the variables that the pattern
+    /// binds.
+    pub fn bind_pattern(
+        &mut self,
+        pattern: &Pattern,
+        pattern_type: Type,
+    ) -> (Witness, HashSet<String>) {
+        let mut set = HashSet::new();
+        let cons_pat = pattern.clone().infer((self, &mut set));
+
+        unify(self.clone(), cons_pat.clone(), pattern_type);
+
+        let problem = Problem::new(cons_pat, vec![wildcard()], vec![pattern.clone()]);
+        (problem.exhaustiveness(self), set)
+    }
+}
@@ -1,25 +1,166 @@
-use crate::{check::Check, context::Ctx, infer::Infer, types::*};
-use atiny_tree::{elaborated::FnBody, r#abstract::*};
+use crate::{check::Check, constraints::Given, context::Ctx, infer::Infer, types::*};
+use atiny_location::Located;
+use atiny_tree::{
+    elaborated::{FnBody, Symbol},
+    r#abstract::*,
+};
 use itertools::Itertools;
-use std::{collections::HashSet, iter, rc::Rc};
+use std::{
+    collections::{HashMap, HashSet},
+    iter,
+    rc::Rc,
+};
 
 impl Ctx {
     pub fn extend_type_sigs(&mut self, iter: impl IntoIterator<Item = TypeDecl>) {
-        iter.into_iter()
+        let (aliases, types): (Vec<_>, Vec<_>) = iter
+            .into_iter()
             .map(|type_decl| type_decl.infer(self))
             .collect_vec()
             .into_iter()
-            .for_each(|constr| constr.infer(self));
+            .partition(|(_, kind)| matches!(kind, TypeDeclKind::Alias(_)));
+
+        let aliases = aliases
+            .into_iter()
+            .filter_map(|(name, kind)| match kind {
+                TypeDeclKind::Alias(typ) => Some((name, typ)),
+                _ => None,
+            })
+            .collect();
+
+        self.expand_aliases(&aliases);
+        types.into_iter().for_each(|constr| constr.infer(self));
+
+        // The parameters that are not applied to anything are types of values.
+        for sig in self.signatures.types.values() {
+            sig.kind.default_holes();
+        }
+    }
+
+    /// Expands every alias after the aliases that it uses, so the uses are only substitutions. The
+    /// aliases in a cycle are reported and expanded into errors.
+    fn expand_aliases(&mut self, aliases: &HashMap<String, TypeNode>) {
+        let mut expanded = HashSet::new();
+        let mut cyclic = HashSet::new();
+
+        for name in aliases.keys().sorted() {
+            let mut path = Vec::new();
+            self.expand_alias(name, aliases, &mut path, &mut expanded, &mut cyclic);
+        }
     }
 
+    fn expand_alias(
+        &mut self,
+        name: &String,
+        aliases: &HashMap<String, TypeNode>,
+        path: &mut Vec<String>,
+        expanded: &mut HashSet<String>,
+        cyclic: &mut HashSet<String>,
+    ) {
+        if expanded.contains(name) {
+            return;
+        }
+
+        let typ = &aliases[name];
+        let mut used = Vec::new();
+        type_names(typ, &mut used);
+
+        path.push(name.clone());
+
+        for used in used {
+            if !aliases.contains_key(&used.data) {
+                continue;
+            }
+
+            if let Some(start) = path.iter().position(|name| *name == used.data) {
+                let cycle = path[start..]
+                    .iter()
+                    .chain(iter::once(&used.data))
+                    .join(" -> ");
+                self.set_position(used.location);
+                self.error(format!("cyclic type alias {cycle}"));
+                cyclic.extend(path[start..].iter().cloned());
+            } else {
+                self.expand_alias(&used.data, aliases, path, expanded, cyclic);
+            }
+        }
+
+        path.pop();
+        expanded.insert(name.clone());
+
+        if cyclic.contains(name) {
+            return;
+        }
+
+        // The parameters are the only type variables of an alias.
+        let mut alias_ctx = self.clone();
+        alias_ctx.typ_map = self.signatures.types[name].type_variables();
+
+        let expansion = typ.infer(alias_ctx);
+        let sig = self.signatures.types.get_mut(name).unwrap();
+        sig.value = TypeValue::Alias(expansion);
+    }
+
+    /// The signatures of the annotated functions are known before their bodies, the other ones are
+    /// inferred in groups of functions that call each other, so they can be generalized.
     pub fn extend_fun_sigs(&mut self, iter: impl IntoIterator<Item = FnDecl>) -> Vec<FnBody<Type>> {
-        iter.into_iter()
+        let (annotated, inferred): (Vec<_>, Vec<_>) =
+            iter.into_iter().partition(FnDecl::is_annotated);
+
+        let annotated = annotated
+            .into_iter()
             .map(|fun_decl| fun_decl.infer(self))
-            .collect_vec()
+            .collect_vec();
+
+        let mut bodies = self.infer_fn_groups(inferred);
+        bodies.extend(annotated.into_iter().map(|body| body.infer(self)));
+        bodies
+    }
+
+    /// Infers the functions in the order of the strongly connected components of their call
+    /// graph, so every function is generalized before it is used by the next groups.
+    fn infer_fn_groups(&mut self, decls: Vec<FnDecl>) -> Vec<FnBody<Type>> {
+        let indices: HashMap<_, _> = decls
+            .iter()
+            .enumerate()
+            .map(|(index, decl)| (decl.name.clone(), index))
+            .collect();
+
+        let graph = decls
+            .iter()
+            .map(|decl| {
+                let mut names = HashSet::new();
+                references(&decl.body, &mut names);
+                let edges = names.iter().filter_map(|name| indices.get(name).copied());
+                edges.sorted_unstable().collect_vec()
+            })
+            .collect_vec();
+
+        let mut decls = decls.into_iter().map(Some).collect_vec();
+
+        strongly_connected_components(&graph)
             .into_iter()
-            .map(|body| body.infer(self))
+            .flat_map(|component| {
+                let group = component.iter().map(|&index| decls[index].take().unwrap());
+                group.collect_vec().infer(self)
+            })
             .collect()
     }
+
+    /// Binds the patterns of the parameters of a function, they must be irrefutable.
+    fn bind_arguments(&mut self, args: &[(Pattern, Type)]) {
+        for (arg_pat, arg_type) in args {
+            let witness = self.single_exhaustiveness(arg_pat, arg_type.clone());
+
+            if let Err(err) = witness.result() {
+                self.set_position(arg_pat.location);
+                self.error(format!(
+                    "refutable pattern in function argument. pattern `{}` not covered",
+                    err
+                ));
+            };
+        }
+    }
 }
 
 impl Infer for Vec<TopLevel> {
@@ -27,11 +168,24 @@ impl Infer for Vec<TopLevel> {
     type Return = Vec<FnBody<Type>>;
 
     fn infer(self, ctx: Self::Context<'_>) -> Self::Return {
-        let mut fn_vec = Vec::new();
-        let top_iter = TopIter::new(self, &mut fn_vec);
+        let mut decls = Declarations::default();
+        let top_iter = TopIter::new(self, &mut decls);
 
         ctx.extend_type_sigs(top_iter);
-        ctx.extend_fun_sigs(fn_vec)
+
+        // They are collected backwards, but the first declarations take the names.
+        ctx.extend_class_sigs(decls.classes.into_iter().rev());
+        ctx.extend_effect_sigs(decls.effects.into_iter().rev());
+
+        // The methods of the instances can use all of the functions.
+        let instances = ctx.extend_instance_sigs(decls.instances.into_iter().rev());
+        let mut bodies = ctx.extend_fun_sigs(decls.functions);
+
+        for instance in instances {
+            bodies.extend(instance.infer(ctx));
+        }
+
+        bodies
     }
 }
 
@@ -43,11 +197,20 @@ impl Infer for TypeDecl {
         let value = match self.constructors {
             TypeDeclKind::Sum(_) => TypeValue::Sum(Vec::new()),
             TypeDeclKind::Product(_) => TypeValue::Product(Vec::new()),
+            // It's expanded once all the types are declared.
+            TypeDeclKind::Alias(_) => TypeValue::Alias(Rc::new(MonoType::Error)),
         };
 
+        // The kinds of the parameters are inferred by their uses in all of the declarations.
+        let kind = self
+            .params
+            .iter()
+            .rfold(Kind::star(), |kind, _| Kind::new_hole().arrow(kind));
+
         let type_sig = TypeSignature {
             name: self.name.clone(),
             params: self.params.clone(),
+            kind,
             value,
         };
 
@@ -68,6 +231,7 @@ impl Infer for (String, TypeDeclKind) {
         match kind {
             TypeDeclKind::Sum(constrs) => name.zip(constrs).for_each(|constr| constr.infer(ctx)),
             TypeDeclKind::Product(fields) => name.zip(fields).for_each(|field| field.infer(ctx)),
+            TypeDeclKind::Alias(_) => unreachable!("aliases are expanded before"),
         }
     }
 }
@@ -79,11 +243,11 @@ impl Infer for (&str, Field) {
     fn infer(self, ctx: Self::Context<'_>) -> Self::Return {
         let (decl_name, field) = self;
 
-        let Some(TypeSignature { params, .. }) = &ctx.signatures.types.get(decl_name) else {
+        let Some(sig) = ctx.signatures.types.get(decl_name) else {
             panic!("The String should be a valid type signature name on the Ctx");
         };
 
-        let new_ctx = ctx.extend_types(params);
+        let new_ctx = ctx.extend_kinded_types(sig.type_variables());
 
         let mono = field.ty.infer(new_ctx);
 
@@ -108,16 +272,13 @@ impl Infer for (&str, Constructor) {
     fn infer(self, ctx: Self::Context<'_>) -> Self::Return {
         let (decl_name, constr) = self;
 
-        let Some(TypeSignature { params, .. }) = &ctx.signatures.types.get(decl_name) else {
+        let Some(sig) = ctx.signatures.types.get(decl_name) else {
             panic!("The String should be a valid type signature name on the Ctx");
         };
 
-        let application = Rc::new(MonoType::Application(
-            decl_name.to_string(),
-            params.iter().cloned().map(MonoType::var).collect(),
-        ));
-
-        let new_ctx = ctx.extend_types(params);
+        let params = sig.params.clone();
+        let application = sig.application();
+        let new_ctx = ctx.extend_kinded_types(sig.type_variables());
 
         let args: Vec<_> = constr
             .types
@@ -127,7 +288,7 @@ impl Infer for (&str, Constructor) {
 
         let value = Rc::new(ConstructorSignature::new(
             constr.name.clone(),
-            params.clone(),
+            params,
             MonoType::rfold_arrow(args.iter().cloned(), application),
             args,
         ));
@@ -151,8 +312,12 @@ impl Infer for FnDecl {
     fn infer(self, ctx: Self::Context<'_>) -> Self::Return {
         let mut set = HashSet::new();
 
-        for (_, ty) in &self.params {
-            ctx.free_variables(ty, &mut set);
+        for typ in self.params.iter().flat_map(|param| &param.typ) {
+            ctx.free_variables(typ, &mut set);
+        }
+
+        if let Some(effects) = &self.effects {
+            ctx.free_effect_variables(effects, &mut set);
         }
 
         let new_ctx = ctx.extend_types(set.iter());
@@ -160,14 +325,32 @@ impl Infer for FnDecl {
         let args: Vec<_> = self
             .params
             .iter()
-            .map(|(a, p)| (a.to_owned(), p.infer(new_ctx.clone())))
+            .flat_map(|Param { pat, typ }| {
+                Some((pat.to_owned(), typ.as_ref()?.infer(new_ctx.clone())))
+            })
             .collect();
 
-        let ret = self.ret.infer(new_ctx);
+        let ret = self
+            .ret
+            .as_ref()
+            .expect("only annotated functions have a known signature");
+        let ret = ret.infer(new_ctx.clone());
 
-        let entire_type = TypeScheme::new(
+        let effects = self
+            .effects
+            .as_ref()
+            .map_or_else(MonoType::pure, |effects| new_ctx.effect_row(effects));
+
+        let constraints = self
+            .constraints
+            .iter()
+            .filter_map(|constraint| new_ctx.constraint(constraint))
+            .collect();
+
+        let entire_type = TypeScheme::qualified(
             set.into_iter().collect(),
-            MonoType::rfold_arrow(args.iter().map(|(_, ty)| ty.clone()), ret),
+            constraints,
+            MonoType::rfold_arrow_with(args.iter().map(|(_, ty)| ty.clone()), ret, effects),
         );
 
         let sig = DeclSignature::Function(FunctionSignature {
@@ -188,38 +371,339 @@ impl Infer for (String, Expr) {
 
     fn infer(self, ctx: Self::Context<'_>) -> Self::Return {
         let (fn_name, body) = self;
+        let body_location = body.location;
 
         let Some(DeclSignature::Function(sig)) = ctx.signatures.values.get(&fn_name) else {
             panic!("The String should be a valid function signature name on the Ctx");
         };
 
         let mut new_ctx = ctx.extend_types(sig.type_variables());
+        new_ctx.bind_arguments(&sig.args);
+
+        // The effects of `main` are reported instead of checked against its type.
+        new_ctx.effects = if fn_name == MAIN {
+            sig.effects().open(ctx)
+        } else {
+            sig.effects()
+        };
 
-        for (arg_pat, arg_type) in &sig.args {
-            let witness = new_ctx.single_exhaustiveness(arg_pat, arg_type.clone());
+        let givens = sig.entire_type.constraints.iter().cloned().map(Given::new);
+        let givens = givens.collect_vec();
 
-            if let Err(err) = witness.result() {
-                new_ctx.set_position(arg_pat.location);
-                new_ctx.error(format!(
-                    "refutable pattern in function argument. pattern `{}` not covered",
-                    err
-                ));
+        let mark = new_ctx.wanted_mark();
+        let body = body.check(new_ctx.clone(), sig.return_type());
+        new_ctx.solve(mark, &givens, &[], true);
+
+        if fn_name == MAIN {
+            new_ctx.set_position(body_location);
+            new_ctx.unhandled_effects(&new_ctx.effects);
+        }
+
+        FnBody {
+            name: Symbol(fn_name),
+            dictionaries: givens.into_iter().map(|given| given.symbol).collect(),
+            body,
+        }
+    }
+}
+
+/// A group of functions without annotations that call each other. They are monomorphic inside of
+/// the group and generalized after all of their bodies are inferred.
+impl Infer for Vec<FnDecl> {
+    type Context<'a> = &'a mut Ctx;
+    type Return = Vec<FnBody<Type>>;
+
+    fn infer(self, ctx: Self::Context<'_>) -> Self::Return {
+        let level_ctx = ctx.level_up();
+        let mut group_ctx = level_ctx.clone();
+        let mut signatures = Vec::with_capacity(self.len());
+
+        for decl in &self {
+            if let Some(constraint) = decl.constraints.first() {
+                ctx.set_position(constraint.class.location);
+                ctx.error("only annotated functions can declare constraints".to_string());
+            }
+
+            let mut set = HashSet::new();
+
+            for typ in decl
+                .params
+                .iter()
+                .flat_map(|param| &param.typ)
+                .chain(&decl.ret)
+            {
+                ctx.free_variables(typ, &mut set);
+            }
+
+            if let Some(effects) = &decl.effects {
+                ctx.free_effect_variables(effects, &mut set);
+            }
+
+            let type_ctx = level_ctx.extend_types(set.iter());
+            let annotation = |typ: &Option<TypeNode>| {
+                typ.as_ref()
+                    .map_or_else(|| type_ctx.new_hole(), |typ| typ.infer(type_ctx.clone()))
             };
+
+            let args = decl
+                .params
+                .iter()
+                .map(|param| (param.pat.clone(), annotation(&param.typ)))
+                .collect_vec();
+
+            let ret = annotation(&decl.ret);
+
+            let effects = decl.effects.as_ref().map_or_else(
+                || type_ctx.new_effect_hole(),
+                |effects| type_ctx.effect_row(effects),
+            );
+
+            let types = args.iter().map(|(_, typ)| typ.clone());
+            let mono = MonoType::rfold_arrow_with(types, ret.clone(), effects.clone());
+
+            group_ctx = group_ctx.extend(decl.name.clone(), mono.to_poly());
+            signatures.push((set, args, ret, effects, mono));
+        }
+
+        let mark = ctx.wanted_mark();
+        let mut bodies = Vec::with_capacity(self.len());
+
+        for (decl, (set, args, ret, effects, _)) in self.iter().zip(&signatures) {
+            let mut body_ctx = group_ctx.extend_types(set.iter());
+            body_ctx.bind_arguments(args);
+
+            body_ctx.effects = if decl.name == MAIN {
+                effects.open(&body_ctx)
+            } else {
+                effects.clone()
+            };
+
+            bodies.push(decl.body.check(body_ctx.clone(), ret.clone()));
+
+            if decl.name == MAIN {
+                body_ctx.set_position(decl.body.location);
+                body_ctx.unhandled_effects(&body_ctx.effects);
+            }
+        }
+
+        // The functions of the group share the constraints that are generalized.
+        let monos = signatures
+            .iter()
+            .map(|(.., mono)| mono.clone())
+            .collect_vec();
+        let givens = ctx.solve(mark, &[], &monos, true);
+        MonoType::close_effects(&monos, ctx);
+
+        let predicates = givens.iter().map(|given| given.predicate.clone());
+        let predicates = predicates.collect_vec();
+        let symbols = givens.into_iter().map(|given| given.symbol).collect_vec();
+
+        let mut fn_bodies = Vec::with_capacity(self.len());
+
+        for ((decl, (set, args, .., mono)), body) in self.into_iter().zip(signatures).zip(bodies) {
+            let scheme = mono.generalize_qualified(ctx.clone(), &predicates);
+            let names = set.into_iter().sorted().chain(scheme.names.iter().cloned());
+            let entire_type = TypeScheme::qualified(
+                names.collect(),
+                scheme.constraints.clone(),
+                scheme.mono.clone(),
+            );
+
+            let patterns = args.into_iter().map(|(pat, _)| pat);
+            let args = patterns.zip(scheme.mono.clone().iter()).collect();
+
+            let sig = DeclSignature::Function(FunctionSignature {
+                name: decl.name.clone(),
+                args,
+                entire_type,
+            });
+
+            ctx.signatures.values.insert(decl.name.clone(), sig);
+
+            fn_bodies.push(FnBody {
+                name: Symbol(decl.name),
+                dictionaries: symbols.clone(),
+                body,
+            });
+        }
+
+        fn_bodies
+    }
+}
+
+/// Collects the names of the types used in a type.
+fn type_names(typ: &TypeNode, names: &mut Vec<Located<String>>) {
+    match &typ.data {
+        TypeKind::Variable(v) => names.push(Located::new(typ.location, v.name.clone())),
+        TypeKind::Arrow(arrow) => {
+            type_names(&arrow.left, names);
+            type_names(&arrow.right, names);
+
+            for effect in arrow.effects.iter().flat_map(|row| &row.effects) {
+                if let TypeKind::Application(app) = &effect.data {
+                    app.args.iter().for_each(|arg| type_names(arg, names));
+                }
+            }
+        }
+        TypeKind::Forall(forall) => type_names(&forall.body, names),
+        TypeKind::Application(app) => {
+            names.push(Located::new(typ.location, app.fun.clone()));
+            app.args.iter().for_each(|arg| type_names(arg, names));
+        }
+        TypeKind::Tuple(tuple) => tuple.types.iter().for_each(|typ| type_names(typ, names)),
+        TypeKind::Record(record) => record
+            .fields
+            .iter()
+            .for_each(|(_, typ)| type_names(typ, names)),
+    }
+}
+
+/// Collects the names used in an expression. The ones that are shadowed by local bindings are
+/// also collected, so the call graph may have more edges than needed.
+fn references(expr: &Expr, names: &mut HashSet<String>) {
+    match &expr.data {
+        ExprKind::Atom(AtomKind::Identifier(name)) => {
+            names.insert(name.clone());
+        }
+        ExprKind::Atom(AtomKind::Tuple(exprs)) | ExprKind::Operators(exprs, _) => {
+            exprs.iter().for_each(|expr| references(expr, names));
+        }
+        ExprKind::Atom(_) | ExprKind::Hole(_) | ExprKind::Error => {}
+        ExprKind::Match(scrutinee, clauses) => {
+            references(scrutinee, names);
+
+            for clause in clauses {
+                clause
+                    .guard
+                    .iter()
+                    .for_each(|guard| references(guard, names));
+                references(&clause.expr, names);
+            }
+        }
+        ExprKind::Abstraction(_, expr)
+        | ExprKind::Annotation(expr, _)
+        | ExprKind::Field(expr, _)
+        | ExprKind::Ref(expr)
+        | ExprKind::Deref(expr) => references(expr, names),
+        ExprKind::Application(fun, arg) | ExprKind::Assign(fun, arg) => {
+            references(fun, names);
+            references(arg, names);
+        }
+        ExprKind::RecordCreation(expr, fields) => {
+            references(expr, names);
+            fields
+                .iter()
+                .for_each(|field| references(&field.expr, names));
+        }
+        ExprKind::Record(fields) => fields
+            .iter()
+            .for_each(|field| references(&field.expr, names)),
+        ExprKind::Handle(expr, clauses) => {
+            references(expr, names);
+            clauses
+                .iter()
+                .for_each(|clause| references(&clause.body, names));
+        }
+        ExprKind::Block(statements) => {
+            for statement in statements {
+                match &statement.data {
+                    StatementKind::Let(_, expr) | StatementKind::Expr(expr) => {
+                        references(expr, names);
+                    }
+                    StatementKind::LetRec(bindings) => bindings
+                        .iter()
+                        .for_each(|binding| references(&binding.value, names)),
+                }
+            }
         }
+    }
+}
 
-        FnBody(body.check(new_ctx, sig.return_type()))
+/// Finds the strongly connected components of a graph using the Tarjan's algorithm. A component
+/// comes after all the components that it has edges to.
+fn strongly_connected_components(graph: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    struct Tarjan<'a> {
+        graph: &'a [Vec<usize>],
+        index: Vec<Option<usize>>,
+        low: Vec<usize>,
+        stack: Vec<usize>,
+        on_stack: Vec<bool>,
+        counter: usize,
+        components: Vec<Vec<usize>>,
     }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, node: usize) {
+            self.index[node] = Some(self.counter);
+            self.low[node] = self.counter;
+            self.counter += 1;
+            self.stack.push(node);
+            self.on_stack[node] = true;
+
+            for &next in &self.graph[node] {
+                match self.index[next] {
+                    None => {
+                        self.visit(next);
+                        self.low[node] = self.low[node].min(self.low[next]);
+                    }
+                    Some(index) if self.on_stack[next] => {
+                        self.low[node] = self.low[node].min(index);
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            if Some(self.low[node]) == self.index[node] {
+                let mut component = Vec::new();
+
+                while let Some(top) = self.stack.pop() {
+                    self.on_stack[top] = false;
+                    component.push(top);
+
+                    if top == node {
+                        break;
+                    }
+                }
+
+                component.sort_unstable();
+                self.components.push(component);
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        graph,
+        index: vec![None; graph.len()],
+        low: vec![0; graph.len()],
+        stack: Vec::new(),
+        on_stack: vec![false; graph.len()],
+        counter: 0,
+        components: Vec::new(),
+    };
+
+    for node in 0..graph.len() {
+        if tarjan.index[node].is_none() {
+            tarjan.visit(node);
+        }
+    }
+
+    tarjan.components
 }
 
 impl Ctx {
-    fn free_variables(&self, ty: &TypeNode, set: &mut HashSet<String>) {
+    /// Collects the type variables of a type that are not bound by the context.
+    pub fn free_variables(&self, ty: &TypeNode, set: &mut HashSet<String>) {
         match &ty.data {
             TypeKind::Arrow(arrow) => {
                 self.free_variables(&arrow.left, set);
                 self.free_variables(&arrow.right, set);
+
+                if let Some(effects) = &arrow.effects {
+                    self.free_effect_variables(effects, set);
+                }
             }
             TypeKind::Variable(v) => {
-                if !self.typ_map.contains(&v.name) {
+                if !self.typ_map.contains_key(&v.name) && self.lookup_type(&v.name).is_none() {
                     set.insert(v.name.clone());
                 }
             }
@@ -239,18 +723,59 @@ impl Ctx {
                     self.free_variables(arg, set);
                 }
             }
+            TypeKind::Record(record) => {
+                for (_, typ) in &record.fields {
+                    self.free_variables(typ, set);
+                }
+
+                if let Some(rest) = &record.rest {
+                    if !self.typ_map.contains_key(&rest.data) {
+                        set.insert(rest.data.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Collects the type variables of an effect row that are not bound by the context, the names
+    /// of the effects are not variables.
+    pub fn free_effect_variables(&self, row: &EffectRowNode, set: &mut HashSet<String>) {
+        for effect in &row.effects {
+            if let TypeKind::Application(app) = &effect.data {
+                for arg in &app.args {
+                    self.free_variables(arg, set);
+                }
+            }
+        }
+
+        if let Some(rest) = &row.rest {
+            if !self.typ_map.contains_key(&rest.data) {
+                set.insert(rest.data.clone());
+            }
         }
     }
 }
 
+/// The function that runs the program, its effects are not handled by anything.
+const MAIN: &str = "main";
+
+/// The declarations of a program that are not types.
+#[derive(Default)]
+struct Declarations {
+    functions: Vec<FnDecl>,
+    classes: Vec<Located<ClassDecl>>,
+    instances: Vec<Located<InstanceDecl>>,
+    effects: Vec<Located<EffectDecl>>,
+}
+
 struct TopIter<'a> {
     vec: Vec<TopLevel>,
-    fn_vec: &'a mut Vec<FnDecl>,
+    decls: &'a mut Declarations,
 }
 
 impl<'a> TopIter<'a> {
-    fn new(vec: Vec<TopLevel>, fn_vec: &'a mut Vec<FnDecl>) -> Self {
-        Self { vec, fn_vec }
+    fn new(vec: Vec<TopLevel>, decls: &'a mut Declarations) -> Self {
+        Self { vec, decls }
     }
 }
 
@@ -262,9 +787,25 @@ impl<'a> Iterator for TopIter<'a> {
             Some(top) => match top.data {
                 TopLevelKind::TypeDecl(typ) => Some(typ),
                 TopLevelKind::FnDecl(fnd) => {
-                    self.fn_vec.push(fnd);
+                    self.decls.functions.push(fnd);
+                    self.next()
+                }
+                TopLevelKind::Class(class) => {
+                    self.decls.classes.push(Located::new(top.location, class));
+                    self.next()
+                }
+                TopLevelKind::Instance(instance) => {
+                    self.decls
+                        .instances
+                        .push(Located::new(top.location, instance));
+                    self.next()
+                }
+                TopLevelKind::Effect(effect) => {
+                    self.decls.effects.push(Located::new(top.location, effect));
                     self.next()
                 }
+                // Fixities are only used by the parser to resolve the infix operators.
+                TopLevelKind::Fixity(_) => self.next(),
             },
             None => None,
         }
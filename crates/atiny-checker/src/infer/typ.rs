@@ -1,67 +1,240 @@
 //! Type inference for types on type annotations.
+//!
+//! The kinds of the types are inferred together with them, so a type constructor is only applied to
+//! types of the kinds of its parameters.
 
 use super::Infer;
-use crate::{context::*, types::*};
+use crate::{context::*, types::*, unify::unify_kinds};
 
-use atiny_tree::r#abstract::{TypeKind, TypeNode};
+use atiny_tree::r#abstract::{EffectRowNode, TypeKind, TypeNode, VariableNode};
 use std::rc::Rc;
 
 impl Infer for &TypeNode {
     type Context<'a> = Ctx;
     type Return = Type;
 
-    fn infer(self, mut ctx: Self::Context<'_>) -> Self::Return {
-        ctx.set_position(self.location);
+    fn infer(self, ctx: Self::Context<'_>) -> Self::Return {
+        ctx.check_kind(self, &Kind::star())
+    }
+}
+
+impl Ctx {
+    /// Infers a type that must have the expected kind.
+    pub fn check_kind(&self, node: &TypeNode, expected: &Rc<Kind>) -> Type {
+        let (typ, kind) = self.infer_kind(node);
+
+        if matches!(&*typ, MonoType::Error) || unify_kinds(&kind, expected) {
+            typ
+        } else {
+            let mut ctx = self.clone();
+            ctx.set_position(node.location);
+            let shown = Names::new(&[&typ]).name(&typ);
+            ctx.new_error(format!(
+                "kind mismatch: expected '{}' but '{}' has kind '{}'",
+                expected, shown, kind
+            ))
+        }
+    }
 
-        match &self.data {
+    /// Infers a type together with its kind.
+    pub fn infer_kind(&self, node: &TypeNode) -> (Type, Rc<Kind>) {
+        let mut ctx = self.clone();
+        ctx.set_position(node.location);
+
+        match &node.data {
             TypeKind::Arrow(arrow) => {
-                let left = arrow.left.infer(ctx.clone());
-                let right = arrow.right.infer(ctx);
-                left.arrow(right)
+                let left = ctx.check_kind(&arrow.left, &Kind::star());
+                let right = ctx.check_kind(&arrow.right, &Kind::star());
+
+                let effects = arrow
+                    .effects
+                    .as_ref()
+                    .map_or_else(MonoType::pure, |effects| ctx.effect_row(effects));
+
+                (left.arrow_with(right, effects), Kind::star())
             }
 
-            TypeKind::Variable(v) => {
-                if ctx.signatures.types.get(&v.name).is_some() {
-                    MonoType::typ(v.name.to_owned())
-                } else if ctx.typ_map.contains(&v.name) {
-                    MonoType::var(v.name.to_owned())
-                } else {
-                    ctx.new_error(format!("unbound type variable '{}'", v.name))
+            TypeKind::Variable(v) => match ctx.signatures.types.get(&v.name) {
+                Some(sig) if matches!(sig.value, TypeValue::Alias(_)) => {
+                    (ctx.apply_alias(sig, Vec::new()), Kind::star())
                 }
-            }
+                Some(sig) => (MonoType::typ(v.name.clone()), sig.kind.clone()),
+                None => ctx.typ_map.get(&v.name).map_or_else(
+                    || {
+                        let msg = format!("unbound type variable '{}'", v.name);
+                        (ctx.new_error(msg), Kind::star())
+                    },
+                    |kind| (MonoType::var(v.name.clone()), kind.clone()),
+                ),
+            },
 
-            TypeKind::Tuple(tuple) => Rc::new(MonoType::Tuple(
-                tuple
-                    .types
-                    .iter()
-                    .map(|typ| typ.infer(ctx.clone()))
-                    .collect(),
-            )),
+            TypeKind::Tuple(tuple) => {
+                let types = tuple.types.iter();
+                let types = types.map(|typ| ctx.check_kind(typ, &Kind::star()));
+                (MonoType::tuple(types.collect()), Kind::star())
+            }
 
-            // TODO: Error when try to infer a forall inside other types.
             TypeKind::Forall(forall) => {
-                TypeScheme {
-                    names: forall.args.clone(),
-                    mono: forall.body.infer(ctx.extend_types(&forall.args)),
+                let body_ctx = ctx.extend_types(&forall.args);
+                let mono = body_ctx.check_kind(&forall.body, &Kind::star());
+                let scheme = TypeScheme::new(forall.args.clone(), mono);
+                (Rc::new(MonoType::Forall(scheme)), Kind::star())
+            }
+
+            TypeKind::Record(record) => {
+                let mut fields = Vec::with_capacity(record.fields.len());
+
+                for (name, typ) in &record.fields {
+                    if fields.iter().any(|(other, _)| other == name) {
+                        return (
+                            ctx.new_error(format!("field '{name}' is duplicated")),
+                            Kind::star(),
+                        );
+                    }
+
+                    fields.push((name.clone(), ctx.check_kind(typ, &Kind::star())));
+                }
+
+                fields.sort_by(|(l, _), (r, _)| l.cmp(r));
+
+                let rest = record.rest.as_ref().map(|rest| {
+                    let node = rest
+                        .clone()
+                        .map(|name| TypeKind::Variable(VariableNode { name }));
+                    ctx.check_kind(&node, &Kind::row())
+                });
+
+                (Rc::new(MonoType::Record(fields, rest)), Kind::star())
+            }
+
+            TypeKind::Application(app) => {
+                let (fun, mut kind) = match ctx.signatures.types.get(&app.fun) {
+                    Some(sig) if matches!(sig.value, TypeValue::Alias(_)) => {
+                        let kinds = sig.kind.params();
+
+                        let args = app.args.iter().enumerate().map(|(i, arg)| {
+                            kinds.get(i).map_or_else(
+                                || ctx.infer_kind(arg).0,
+                                |kind| ctx.check_kind(arg, kind),
+                            )
+                        });
+
+                        return (ctx.apply_alias(sig, args.collect()), Kind::star());
+                    }
+                    Some(sig) => (MonoType::typ(app.fun.clone()), sig.kind.clone()),
+                    None => match ctx.typ_map.get(&app.fun) {
+                        Some(kind) => (MonoType::var(app.fun.clone()), kind.clone()),
+                        None => {
+                            let msg = format!("unbound variable '{}'", app.fun);
+                            return (ctx.new_error(msg), Kind::star());
+                        }
+                    },
+                };
+
+                let mut args = Vec::with_capacity(app.args.len());
+
+                for arg in &app.args {
+                    let (param, result) = match &*kind.prune() {
+                        Kind::Arrow(param, result) => (param.clone(), result.clone()),
+                        Kind::Hole(_) => {
+                            let (param, result) = (Kind::new_hole(), Kind::new_hole());
+                            unify_kinds(&kind, &param.clone().arrow(result.clone()));
+                            (param, result)
+                        }
+                        Kind::Star | Kind::Row | Kind::Effect => {
+                            let msg = format!(
+                                "expected {} arguments but got {} in type",
+                                args.len(),
+                                app.args.len()
+                            );
+                            return (ctx.new_error(msg), Kind::star());
+                        }
+                    };
+
+                    args.push(ctx.check_kind(arg, &param));
+                    kind = result;
                 }
-                .instantiate(ctx)
-                .0
+
+                (MonoType::apply(fun, args), kind)
             }
+        }
+    }
 
-            TypeKind::Application(app) => match ctx.signatures.types.get(&app.fun) {
-                Some(sig) if sig.params.len() == app.args.len() => Rc::new(MonoType::Application(
-                    sig.name.clone(),
-                    app.args.iter().map(|typ| typ.infer(ctx.clone())).collect(),
-                )),
+    /// Infers a row of effects, its effects must be declared and applied to all of their
+    /// parameters.
+    pub fn effect_row(&self, row: &EffectRowNode) -> Type {
+        let mut ctx = self.clone();
+        let mut effects = Vec::with_capacity(row.effects.len());
 
-                Some(sig) => ctx.new_error(format!(
-                    "expected {} arguments but got {} in type",
+        for node in &row.effects {
+            ctx.set_position(node.location);
+
+            let (name, args) = match &node.data {
+                TypeKind::Variable(v) => (&v.name, &[][..]),
+                TypeKind::Application(app) => (&app.fun, &app.args[..]),
+                _ => return ctx.new_error(format!("'{}' is not an effect", node.data)),
+            };
+
+            let Some(sig) = ctx.signatures.effects.get(name) else {
+                if ctx.lookup_type(name).is_some() {
+                    return ctx.new_error(format!("'{name}' is not an effect"));
+                }
+
+                return ctx.new_error(format!("unbound effect '{name}'"));
+            };
+
+            if sig.params.len() != args.len() {
+                return ctx.new_error(format!(
+                    "the effect '{}' expects {} arguments but got {}",
+                    name,
                     sig.params.len(),
-                    app.args.len()
-                )),
+                    args.len()
+                ));
+            }
 
-                None => ctx.new_error(format!("unbound variable '{}'", app.fun)),
-            },
+            if effects.iter().any(|(other, _)| other == name) {
+                return ctx.new_error(format!("the effect '{name}' is duplicated"));
+            }
+
+            let args = args
+                .iter()
+                .zip(&sig.params)
+                .map(|(arg, (_, kind))| ctx.check_kind(arg, kind));
+
+            let effect = MonoType::Application(name.clone(), args.collect());
+            effects.push((name.clone(), Rc::new(effect)));
+        }
+
+        effects.sort_by(|(l, _), (r, _)| l.cmp(r));
+
+        let rest = row.rest.as_ref().map(|rest| {
+            let node = rest
+                .clone()
+                .map(|name| TypeKind::Variable(VariableNode { name }));
+            self.check_kind(&node, &Kind::effect())
+        });
+
+        Rc::new(MonoType::Effects(effects, rest))
+    }
+
+    /// Expands an alias that is applied to its arguments, it must be applied to all of its
+    /// parameters.
+    fn apply_alias(&self, sig: &TypeSignature, args: Vec<Type>) -> Type {
+        let TypeValue::Alias(typ) = &sig.value else {
+            unreachable!("only aliases are expanded")
+        };
+
+        if sig.params.len() != args.len() {
+            return self.new_error(format!(
+                "the type alias '{}' expects {} arguments but got {}",
+                sig.name,
+                sig.params.len(),
+                args.len()
+            ));
         }
+
+        let substs = sig.params.iter().cloned().zip(args.iter().cloned());
+        let expansion = typ.substitute(&substs.collect());
+        Rc::new(MonoType::Alias(sig.name.clone(), args, expansion))
     }
 }
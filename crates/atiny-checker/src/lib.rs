@@ -1,6 +1,7 @@
 //! This module is useful for type checking [Expr] and [TopLevel] definitions using the
 //! hindley-milner type system extended with type classes and linear types.
 
+pub mod constraints;
 pub mod context;
 pub mod types;
 pub mod unify;
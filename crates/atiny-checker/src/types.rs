@@ -6,7 +6,7 @@ use std::{
     collections::{BTreeMap, HashSet},
     fmt::{self, Display},
     hash::{Hash, Hasher},
-    ptr::addr_of,
+    iter,
     rc::Rc,
 };
 
@@ -17,6 +17,12 @@ use super::context::Ctx;
 
 pub type Type = Rc<MonoType>;
 
+/// The built-in type of the mutable references.
+pub const REF: &str = "Ref";
+
+/// The name of the holes of the rows of effects, they are not shown while they are not known.
+pub const EFFECT_HOLE: &str = "'e";
+
 /// A type scheme is a prenex polymorphic construction that is used to express value dependency on
 /// types. E.g.
 ///
@@ -26,38 +32,75 @@ pub type Type = Rc<MonoType>;
 #[derive(Debug)]
 pub struct TypeScheme {
     pub names: Vec<String>,
+
+    /// The constraints on the type variables, like the `Show a` of `forall a. Show a => a -> a`.
+    /// Only the schemes of the bindings have them, not the ones inside of other types.
+    pub constraints: Vec<Predicate>,
     pub mono: Type,
 }
 
 impl TypeScheme {
     pub fn new(names: Vec<String>, mono: Type) -> Rc<Self> {
-        Rc::new(Self { names, mono })
+        Self::qualified(names, Vec::new(), mono)
+    }
+
+    pub fn qualified(names: Vec<String>, constraints: Vec<Predicate>, mono: Type) -> Rc<Self> {
+        Rc::new(Self {
+            names,
+            constraints,
+            mono,
+        })
     }
 
     pub fn instantiate(&self, ctx: Ctx) -> (Type, Vec<Type>) {
         let mut types = Vec::new();
 
         for _ in &self.names {
-            types.push(MonoType::new_hole(ctx.new_name(), ctx.level));
+            types.push(ctx.new_hole());
         }
 
-        (self.instantiate_with(&types), types)
+        let typ = self.instantiate_with(&types);
+
+        // A higher rank type that is used is instantiated as well.
+        match &*typ.clone().flatten() {
+            MonoType::Forall(scheme) => {
+                let (typ, inner) = scheme.instantiate(ctx);
+                types.extend(inner);
+                (typ, types)
+            }
+            _ => (typ, types),
+        }
     }
 
     pub fn instantiate_with(&self, types: &[Type]) -> Type {
-        let substitutions = self
-            .names
+        self.mono.substitute(&self.substitutions(types))
+    }
+
+    /// The constraints of the scheme for the types of its variables.
+    pub fn constraints_with(&self, types: &[Type]) -> Vec<Predicate> {
+        let substitutions = self.substitutions(types);
+
+        self.constraints
+            .iter()
+            .map(|predicate| predicate.substitute(&substitutions))
+            .collect()
+    }
+
+    fn substitutions(&self, types: &[Type]) -> BTreeMap<String, Type> {
+        self.names
             .iter()
             .cloned()
             .zip(types.iter().cloned())
-            .collect::<BTreeMap<String, Type>>();
-
-        self.mono.substitute(&substitutions)
+            .collect()
     }
 }
 
 impl Display for TypeScheme {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.names.is_empty() {
+            return write!(f, "{}", self.mono);
+        }
+
         write!(f, "forall ")?;
 
         for (i, name) in self.names.iter().enumerate() {
@@ -67,7 +110,40 @@ impl Display for TypeScheme {
             write!(f, "{}", name)?;
         }
 
-        write!(f, ". {}", self.mono)
+        write!(f, ". ")?;
+
+        if !self.constraints.is_empty() {
+            write!(f, "{} => ", self.constraints.iter().join(", "))?;
+        }
+
+        write!(f, "{}", self.mono)
+    }
+}
+
+/// A constraint that requires a type to be an instance of a class, like `Show a`.
+#[derive(Debug, Clone)]
+pub struct Predicate {
+    pub class: String,
+    pub typ: Type,
+}
+
+impl Predicate {
+    pub fn substitute(&self, substs: &BTreeMap<String, Type>) -> Self {
+        Self {
+            class: self.class.clone(),
+            typ: self.typ.substitute(substs),
+        }
+    }
+
+    /// The name of the dictionary that is received by the functions with this constraint.
+    pub fn symbol(&self) -> String {
+        format!("#{}.{}", self.class, self.typ)
+    }
+}
+
+impl Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.class, self.typ)
     }
 }
 
@@ -104,9 +180,9 @@ impl RefItem {
     }
 }
 
-/// A shared mutable reference to a hole.
+/// A shared mutable reference to a hole, it's identified by an unique id.
 #[derive(Debug, Clone)]
-pub struct Ref(Rc<RefCell<RefItem>>);
+pub struct Ref(usize, Rc<RefCell<RefItem>>);
 
 impl PartialEq for Ref {
     fn eq(&self, other: &Self) -> bool {
@@ -116,7 +192,7 @@ impl PartialEq for Ref {
 
 impl Display for Ref {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let item = self.0.borrow();
+        let item = self.1.borrow();
         write!(f, "ref {{{}, {}}}", item.name, item.data)
     }
 }
@@ -131,7 +207,7 @@ impl Ord for Ref {
 
 impl PartialOrd for Ref {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.identifier().partial_cmp(&other.identifier())
+        Some(self.cmp(other))
     }
 }
 
@@ -142,35 +218,36 @@ impl Hash for Ref {
 }
 
 impl Ref {
-    pub fn identifier(&self) -> u64 {
-        addr_of!(*self.0.as_ref().borrow()) as u64
+    pub fn identifier(&self) -> usize {
+        self.0
     }
 
-    pub fn new(name: String, level: usize) -> Self {
-        Self(Rc::new(RefCell::new(RefItem {
-            name,
-            data: Hole::Empty(level),
-        })))
+    pub fn new(id: usize, name: String, level: usize) -> Self {
+        Self(id, Rc::new(RefCell::new(RefItem::new(name, level))))
     }
 
     pub fn fill(&self, typ: Type) {
-        self.0.as_ref().borrow_mut().data = Hole::Filled(typ);
+        self.1.as_ref().borrow_mut().data = Hole::Filled(typ);
     }
 
     pub fn is_empty(&self) -> bool {
-        matches!(self.0.borrow().data, Hole::Empty(_))
+        matches!(self.1.borrow().data, Hole::Empty(_))
     }
 
     pub fn is_filled(&self) -> bool {
-        matches!(self.0.borrow().data, Hole::Filled(_))
+        matches!(self.1.borrow().data, Hole::Filled(_))
     }
 
     pub fn get(&self) -> Hole {
-        self.0.borrow().data.clone()
+        self.1.borrow().data.clone()
     }
 
     pub fn get_item_mut(&self) -> RefMut<RefItem> {
-        self.0.as_ref().borrow_mut()
+        self.1.as_ref().borrow_mut()
+    }
+
+    pub fn name(&self) -> String {
+        self.1.borrow().name.clone()
     }
 }
 
@@ -179,9 +256,41 @@ impl Ref {
 pub enum MonoType {
     Var(String),
     Tuple(Vec<Type>),
-    Arrow(Type, Type),
+
+    /// A function from a type to another, together with the row of the effects that it performs
+    /// when it's applied.
+    Arrow(Type, Type, Type),
+
     Hole(Ref),
     Application(String, Vec<Type>),
+
+    /// A polymorphic type inside of another type, like the parameter of
+    /// `(forall a. a -> a) -> Int`. It's instantiated when it's used and skolemized when a value
+    /// is checked against it.
+    Forall(Rc<TypeScheme>),
+
+    /// A type variable of a skolemized forall, it's only equal to itself. It has an unique id and
+    /// the level where it was bound, so it can't be used by the holes that are out of its scope.
+    Skolem(String, usize, usize),
+
+    /// An application of a type variable or a hole, like `f a`. It becomes an
+    /// [Self::Application] when the head stands for a type constructor.
+    VarApplication(Type, Vec<Type>),
+
+    /// An application of a type alias together with its expansion, the alias is only kept to be
+    /// shown in the messages.
+    Alias(String, Vec<Type>, Type),
+
+    /// The type of the records with the fields, sorted by name, and a row with the other fields.
+    /// The row is a type variable, a hole or a skolem that stands for more fields, the record
+    /// has no other fields without it. See [Self::row].
+    Record(Vec<(String, Type)>, Option<Type>),
+
+    /// A row of effects, like `{State Int | e}`. The effects are applications of the declared
+    /// effects keyed and sorted by their names, and the rest of the row works like the one of the
+    /// records.
+    Effects(Vec<(String, Type)>, Option<Type>),
+
     Error,
 }
 
@@ -190,14 +299,53 @@ impl Display for MonoType {
         match self {
             Self::Var(name) => write!(f, "{}", name),
             Self::Tuple(t) => write!(f, "({})", t.iter().join(", ")),
-            Self::Arrow(from, to) => write!(f, "({} -> {})", from, to),
+            Self::Arrow(from, to, effects) if effects.is_pure_or_unknown() => {
+                write!(f, "({} -> {})", from, to)
+            }
+            Self::Arrow(from, to, effects) => write!(f, "({} -> {} ! {})", from, to, effects),
             Self::Hole(item) => match item.get() {
                 Hole::Filled(typ) => write!(f, "{}", typ),
-                Hole::Empty(0) => write!(f, "^{}", item.0.borrow().name),
-                Hole::Empty(lvl) => write!(f, "^{lvl}~{}", item.0.borrow().name),
+                Hole::Empty(0) => write!(f, "^{}", item.name()),
+                Hole::Empty(lvl) => write!(f, "^{lvl}~{}", item.name()),
             },
-            Self::Application(name, args) if args.is_empty() => write!(f, "{}", name),
-            Self::Application(name, args) => write!(f, "({} {})", name, args.iter().join(" ")),
+            Self::Application(name, args) | Self::Alias(name, args, _) if args.is_empty() => {
+                write!(f, "{}", name)
+            }
+            Self::Application(name, args) | Self::Alias(name, args, _) => {
+                write!(f, "({} {})", name, args.iter().join(" "))
+            }
+            Self::VarApplication(fun, args) => match &*Self::apply(fun.clone(), args.clone()) {
+                Self::VarApplication(fun, args) => {
+                    write!(f, "({} {})", fun, args.iter().join(" "))
+                }
+                typ => write!(f, "{}", typ),
+            },
+            Self::Forall(scheme) => write!(f, "({})", scheme),
+            Self::Skolem(name, ..) => write!(f, "{}", name),
+            Self::Record(fields, rest) => {
+                let (fields, rest) = record_row(fields, rest);
+                let fields = fields.iter().map(|(name, typ)| format!("{name} : {typ}"));
+                let fields = fields.collect_vec();
+
+                match rest {
+                    Some(rest) if fields.is_empty() => write!(f, "{{ | {rest} }}"),
+                    Some(rest) => write!(f, "{{ {} | {rest} }}", fields.join(", ")),
+                    None if fields.is_empty() => write!(f, "{{}}"),
+                    None => write!(f, "{{ {} }}", fields.join(", ")),
+                }
+            }
+            // The rest of the row is not shown while it's not known.
+            Self::Effects(effects, rest) => {
+                let (effects, rest) = record_row(effects, rest);
+                let effects = effects.iter().map(|(_, effect)| effect_label(effect));
+                let effects = effects.collect_vec();
+
+                match rest.filter(|rest| rest.head_hole().is_none()) {
+                    Some(rest) if effects.is_empty() => write!(f, "{rest}"),
+                    Some(rest) => write!(f, "{{{} | {rest}}}", effects.join(", ")),
+                    None => write!(f, "{{{}}}", effects.join(", ")),
+                }
+            }
             Self::Error => write!(f, "_"),
         }
     }
@@ -205,7 +353,7 @@ impl Display for MonoType {
 
 impl MonoType {
     pub fn get_constructor(self: &Type) -> Option<String> {
-        match &**self {
+        match &*self.clone().flatten() {
             Self::Application(s, _) => Some(s.clone()),
             _ => None,
         }
@@ -215,25 +363,68 @@ impl MonoType {
         TypeIter { typ: self }
     }
 
+    /// Follows the filled holes and the aliases until the type that they stand for.
     pub fn flatten(self: Type) -> Type {
         match &*self {
             Self::Hole(hole) => match hole.get() {
                 Hole::Filled(f) => f.flatten(),
                 Hole::Empty(_) => self,
             },
+            Self::Alias(_, _, typ) => typ.clone().flatten(),
+            Self::VarApplication(fun, args) => Self::apply(fun.clone(), args.clone()),
+            _ => self,
+        }
+    }
+
+    /// Like [Self::flatten], but it stops at the aliases.
+    pub fn prune(self: Type) -> Type {
+        match &*self {
+            Self::Hole(hole) => match hole.get() {
+                Hole::Filled(f) => f.prune(),
+                Hole::Empty(_) => self,
+            },
             _ => self,
         }
     }
 
-    pub fn flatten_back(self: Type) -> Type {
+    pub fn is_forall(self: &Type) -> bool {
+        matches!(&*self.clone().flatten(), Self::Forall(_))
+    }
+
+    /// If there's a forall in the arrows of a type, so it's a higher rank type.
+    pub fn is_higher_rank(self: &Type) -> bool {
+        match &*self.clone().flatten() {
+            Self::Forall(_) => true,
+            Self::Arrow(from, to, _) => from.is_higher_rank() || to.is_higher_rank(),
+            _ => false,
+        }
+    }
+
+    /// Instantiates the forall that a type stands for, so it can be used.
+    pub fn instantiate_forall(self: Type, ctx: Ctx) -> Type {
         match &*self.clone().flatten() {
-            Self::Arrow(_, end) => end.clone().flatten_back(),
+            Self::Forall(scheme) => scheme.instantiate(ctx).0,
             _ => self,
         }
     }
 
     pub fn rfold_arrow<I: DoubleEndedIterator<Item = Type>>(iter: I, end: Type) -> Type {
-        iter.rfold(end, |x, y| y.arrow(x))
+        Self::rfold_arrow_with(iter, end, Self::pure())
+    }
+
+    /// Like [Self::rfold_arrow], but the last arrow performs the effects. The other ones are pure,
+    /// so the partial applications don't perform anything.
+    pub fn rfold_arrow_with<I>(iter: I, end: Type, effects: Type) -> Type
+    where
+        I: DoubleEndedIterator<Item = Type>,
+    {
+        let mut iter = iter.rev();
+
+        let Some(last) = iter.next() else {
+            return end;
+        };
+
+        iter.fold(last.arrow_with(end, effects), |x, y| y.arrow(x))
     }
 
     pub fn substitute(self: &Type, substs: &BTreeMap<String, Type>) -> Type {
@@ -244,9 +435,11 @@ impl MonoType {
                 vec.iter().map(|mono| mono.substitute(substs)).collect(),
             )),
 
-            Self::Arrow(from, to) => {
-                Rc::new(Self::Arrow(from.substitute(substs), to.substitute(substs)))
-            }
+            Self::Arrow(from, to, effects) => Rc::new(Self::Arrow(
+                from.substitute(substs),
+                to.substitute(substs),
+                effects.substitute(substs),
+            )),
 
             Self::Hole(item) => match item.get() {
                 Hole::Filled(typ) => typ.substitute(substs),
@@ -258,15 +451,157 @@ impl MonoType {
                 args.iter().map(|a| a.substitute(substs)).collect(),
             )),
 
+            Self::VarApplication(fun, args) => Self::apply(
+                fun.substitute(substs),
+                args.iter().map(|a| a.substitute(substs)).collect(),
+            ),
+
+            Self::Forall(scheme) => {
+                let substs = substs
+                    .iter()
+                    .filter(|(name, _)| !scheme.names.contains(name))
+                    .map(|(name, typ)| (name.clone(), typ.clone()))
+                    .collect();
+
+                let mono = scheme.mono.substitute(&substs);
+                Rc::new(Self::Forall(TypeScheme::new(scheme.names.clone(), mono)))
+            }
+
+            Self::Skolem(..) => self.clone(),
+
+            Self::Alias(name, args, typ) => Rc::new(Self::Alias(
+                name.clone(),
+                args.iter().map(|a| a.substitute(substs)).collect(),
+                typ.substitute(substs),
+            )),
+
+            Self::Record(fields, rest) => Rc::new(Self::Record(
+                fields
+                    .iter()
+                    .map(|(name, typ)| (name.clone(), typ.substitute(substs)))
+                    .collect(),
+                rest.as_ref().map(|rest| rest.substitute(substs)),
+            )),
+
+            Self::Effects(effects, rest) => Rc::new(Self::Effects(
+                effects
+                    .iter()
+                    .map(|(name, typ)| (name.clone(), typ.substitute(substs)))
+                    .collect(),
+                rest.as_ref().map(|rest| rest.substitute(substs)),
+            )),
+
             Self::Error => self.clone(),
         }
     }
 
+    /// Copies a type with new holes in place of its empty holes, so the copy can be unified
+    /// without filling them. The same hole is replaced by the same copy.
+    pub fn freshen(self: &Type, ctx: &Ctx, holes: &mut Vec<(Ref, Type)>) -> Type {
+        self.map_holes(
+            &mut |item| match holes.iter().find(|(hole, _)| hole == item) {
+                Some((_, copy)) => copy.clone(),
+                None => {
+                    let Hole::Empty(level) = item.get() else {
+                        unreachable!("the holes are pruned")
+                    };
+
+                    let copy = ctx.new_hole_at(level);
+                    holes.push((item.clone(), copy.clone()));
+                    copy
+                }
+            },
+        )
+    }
+
+    /// Copies a type with the types that `f` gives in place of its empty holes.
+    fn map_holes(self: &Type, f: &mut impl FnMut(&Ref) -> Type) -> Type {
+        match &*self.clone().prune() {
+            Self::Hole(item) => f(item),
+
+            Self::Tuple(types) => Rc::new(Self::Tuple(
+                types.iter().map(|typ| typ.map_holes(f)).collect(),
+            )),
+
+            Self::Arrow(from, to, effects) => Rc::new(Self::Arrow(
+                from.map_holes(f),
+                to.map_holes(f),
+                effects.map_holes(f),
+            )),
+
+            Self::Application(name, args) => Rc::new(Self::Application(
+                name.clone(),
+                args.iter().map(|arg| arg.map_holes(f)).collect(),
+            )),
+
+            Self::VarApplication(fun, args) => Rc::new(Self::VarApplication(
+                fun.map_holes(f),
+                args.iter().map(|arg| arg.map_holes(f)).collect(),
+            )),
+
+            Self::Forall(scheme) => {
+                let mono = scheme.mono.map_holes(f);
+                Rc::new(Self::Forall(TypeScheme::new(scheme.names.clone(), mono)))
+            }
+
+            Self::Alias(name, args, typ) => Rc::new(Self::Alias(
+                name.clone(),
+                args.iter().map(|arg| arg.map_holes(f)).collect(),
+                typ.map_holes(f),
+            )),
+
+            Self::Record(fields, rest) => Rc::new(Self::Record(
+                fields
+                    .iter()
+                    .map(|(name, typ)| (name.clone(), typ.map_holes(f)))
+                    .collect(),
+                rest.as_ref().map(|rest| rest.map_holes(f)),
+            )),
+
+            Self::Effects(effects, rest) => Rc::new(Self::Effects(
+                effects
+                    .iter()
+                    .map(|(name, typ)| (name.clone(), typ.map_holes(f)))
+                    .collect(),
+                rest.as_ref().map(|rest| rest.map_holes(f)),
+            )),
+
+            Self::Var(_) | Self::Skolem(..) | Self::Error => self.clone(),
+        }
+    }
+
+    /// Collects the names of the type variables and skolems of a type.
+    fn variables(self: &Type, names: &mut HashSet<String>) {
+        match &*self.clone().prune() {
+            Self::Var(name) | Self::Skolem(name, ..) => {
+                names.insert(name.clone());
+            }
+            Self::Tuple(types) | Self::Application(_, types) | Self::Alias(_, types, _) => {
+                types.iter().for_each(|typ| typ.variables(names));
+            }
+            Self::Arrow(from, to, effects) => {
+                from.variables(names);
+                to.variables(names);
+                effects.variables(names);
+            }
+            Self::VarApplication(fun, args) => {
+                fun.variables(names);
+                args.iter().for_each(|typ| typ.variables(names));
+            }
+            Self::Forall(scheme) => {
+                names.extend(scheme.names.iter().cloned());
+                scheme.mono.variables(names);
+            }
+            Self::Record(fields, rest) | Self::Effects(fields, rest) => {
+                fields.iter().for_each(|(_, typ)| typ.variables(names));
+                rest.iter().for_each(|rest| rest.variables(names));
+            }
+            Self::Hole(_) | Self::Error => {}
+        }
+    }
+
     pub fn to_poly(self: &Type) -> Rc<TypeScheme> {
-        Rc::new(TypeScheme {
-            names: vec![],
-            mono: self.clone(),
-        })
+        TypeScheme::new(vec![], self.clone())
     }
 
     pub fn tuple(vec: Vec<Type>) -> Type {
@@ -281,15 +616,68 @@ impl MonoType {
         Rc::new(Self::Application(name, vec![]))
     }
 
+    /// The type of the mutable references to values of a type.
+    pub fn reference(typ: Type) -> Type {
+        Rc::new(Self::Application(REF.to_string(), vec![typ]))
+    }
+
+    /// A pure function type.
     pub fn arrow(self: Type, to: Type) -> Type {
-        Rc::new(Self::Arrow(self, to))
+        self.arrow_with(to, Self::pure())
+    }
+
+    pub fn arrow_with(self: Type, to: Type, effects: Type) -> Type {
+        Rc::new(Self::Arrow(self, to, effects))
+    }
+
+    /// The empty row of effects.
+    pub fn pure() -> Type {
+        Rc::new(Self::Effects(Vec::new(), None))
+    }
+
+    /// If a row of effects has no effects and no rest, or if it's not known yet.
+    pub fn is_pure_or_unknown(self: &Type) -> bool {
+        match self.row() {
+            (effects, None) => effects.is_empty(),
+            (effects, Some(rest)) => effects.is_empty() && rest.head_hole().is_some(),
+        }
+    }
+
+    /// A row with the effects of another one and a new rest, so it can have more effects.
+    pub fn open(self: &Type, ctx: &Ctx) -> Type {
+        match self.row() {
+            (effects, None) if effects.is_empty() => ctx.new_effect_hole(),
+            (effects, None) => Rc::new(Self::Effects(effects, Some(ctx.new_effect_hole()))),
+            _ => self.clone(),
+        }
     }
 
-    pub fn new_hole(name: String, level: usize) -> Type {
-        Rc::new(Self::Hole(Ref::new(name, level)))
+    pub fn new_hole(id: usize, name: String, level: usize) -> Type {
+        Rc::new(Self::Hole(Ref::new(id, name, level)))
     }
 
-    fn generalize_type(self: &Type, ctx: Ctx, holes: &mut BTreeMap<Ref, String>) -> Type {
+    /// Applies a type to more arguments, the arguments are appended to the ones of the type
+    /// constructor or the type variable that it stands for.
+    pub fn apply(fun: Type, args: Vec<Type>) -> Type {
+        if args.is_empty() {
+            return fun;
+        }
+
+        match &*fun.clone().flatten() {
+            Self::Application(name, init) => {
+                let args = init.iter().cloned().chain(args).collect();
+                Rc::new(Self::Application(name.clone(), args))
+            }
+            Self::VarApplication(fun, init) => {
+                let args = init.iter().cloned().chain(args).collect();
+                Rc::new(Self::VarApplication(fun.clone(), args))
+            }
+            Self::Error => fun,
+            _ => Rc::new(Self::VarApplication(fun, args)),
+        }
+    }
+
+    fn generalize_type(self: &Type, ctx: Ctx, holes: &mut Vec<(Ref, String)>) -> Type {
         match &**self {
             Self::Var(_) => self.clone(),
 
@@ -299,16 +687,32 @@ impl MonoType {
                     .collect(),
             )),
 
-            Self::Arrow(from, to) => Rc::new(Self::Arrow(
+            Self::Arrow(from, to, effects) => Rc::new(Self::Arrow(
                 from.generalize_type(ctx.clone(), holes),
-                to.generalize_type(ctx, holes),
+                to.generalize_type(ctx.clone(), holes),
+                effects.generalize_row(ctx, holes),
             )),
 
             Self::Hole(item) => match item.get() {
                 Hole::Filled(typ) => typ.generalize_type(ctx, holes),
                 Hole::Empty(lvl) if lvl > ctx.level => {
-                    let name = holes.entry(item.clone()).or_insert_with(|| ctx.new_name());
-                    Self::var(name.clone())
+                    let name = match holes.iter().find(|(hole, _)| hole == item) {
+                        Some((_, name)) => name.clone(),
+                        None => {
+                            // The rows are named apart, so their names are skipped.
+                            let vars = holes.iter().filter(|(_, name)| !is_row_name(name)).count();
+                            let name = (0..)
+                                .map(|index| format!("'{}", variable_name(index)))
+                                .filter(|name| !is_row_name(name))
+                                .nth(vars)
+                                .unwrap();
+
+                            holes.push((item.clone(), name.clone()));
+                            name
+                        }
+                    };
+
+                    Self::var(name)
                 }
                 Hole::Empty(_) => self.clone(),
             },
@@ -321,20 +725,424 @@ impl MonoType {
                     .collect(),
             )),
 
+            Self::VarApplication(fun, args) => Self::apply(
+                fun.generalize_type(ctx.clone(), holes),
+                args.iter()
+                    .map(|a| a.generalize_type(ctx.clone(), holes))
+                    .collect(),
+            ),
+
+            Self::Forall(scheme) => {
+                let mono = scheme.mono.generalize_type(ctx, holes);
+                Rc::new(Self::Forall(TypeScheme::new(scheme.names.clone(), mono)))
+            }
+
+            Self::Skolem(..) => self.clone(),
+
+            Self::Alias(name, args, typ) => Rc::new(Self::Alias(
+                name.clone(),
+                args.iter()
+                    .map(|a| a.generalize_type(ctx.clone(), holes))
+                    .collect(),
+                typ.generalize_type(ctx, holes),
+            )),
+
+            Self::Record(fields, rest) => Rc::new(Self::Record(
+                fields
+                    .iter()
+                    .map(|(name, typ)| (name.clone(), typ.generalize_type(ctx.clone(), holes)))
+                    .collect(),
+                rest.as_ref().map(|rest| rest.generalize_type(ctx, holes)),
+            )),
+
+            Self::Effects(effects, rest) => Rc::new(Self::Effects(
+                effects
+                    .iter()
+                    .map(|(name, typ)| (name.clone(), typ.generalize_type(ctx.clone(), holes)))
+                    .collect(),
+                rest.as_ref().map(|rest| rest.generalize_row(ctx, holes)),
+            )),
+
             Self::Error => self.clone(),
         }
     }
 
+    /// Generalizes a row of effects. Its holes don't take the names of the type variables, they
+    /// are named `'e1`, `'e2` and so on.
+    fn generalize_row(self: &Type, ctx: Ctx, holes: &mut Vec<(Ref, String)>) -> Type {
+        match &*self.clone().prune() {
+            Self::Hole(item) if matches!(item.get(), Hole::Empty(lvl) if lvl > ctx.level) => {
+                let name = match holes.iter().find(|(hole, _)| hole == item) {
+                    Some((_, name)) => name.clone(),
+                    None => {
+                        let rows = holes.iter().filter(|(_, name)| is_row_name(name)).count();
+                        let name = format!("'e{}", rows + 1);
+                        holes.push((item.clone(), name.clone()));
+                        name
+                    }
+                };
+
+                Self::var(name)
+            }
+            _ => self.generalize_type(ctx, holes),
+        }
+    }
+
+    /// Fills the holes of the rows of effects that are only performed by the types, and never
+    /// received from their parameters, with the empty row. So the functions that don't depend on
+    /// the effects of other functions are not polymorphic on their effects.
+    pub fn close_effects(types: &[Type], ctx: &Ctx) {
+        let mut rows = Vec::new();
+
+        for typ in types {
+            typ.effect_holes(ctx, true, &mut rows);
+        }
+
+        let received = rows
+            .iter()
+            .filter(|(_, positive)| !positive)
+            .map(|(hole, _)| hole.clone())
+            .collect_vec();
+
+        for (hole, _) in rows {
+            if !received.contains(&hole) && hole.is_empty() {
+                hole.fill(Self::pure());
+            }
+        }
+    }
+
+    /// Collects the holes of the rows of effects above the level of the context, together with
+    /// whether they are in a positive position.
+    fn effect_holes(self: &Type, ctx: &Ctx, positive: bool, rows: &mut Vec<(Ref, bool)>) {
+        match &*self.clone().flatten() {
+            Self::Arrow(from, to, effects) => {
+                from.effect_holes(ctx, !positive, rows);
+                to.effect_holes(ctx, positive, rows);
+
+                let (effects, rest) = effects.row();
+
+                for (_, effect) in effects {
+                    effect.effect_holes(ctx, positive, rows);
+                }
+
+                match rest.and_then(|rest| rest.head_hole()) {
+                    Some((hole, level)) if level > ctx.level => rows.push((hole, positive)),
+                    _ => {}
+                }
+            }
+            Self::Tuple(types) | Self::Application(_, types) => types
+                .iter()
+                .for_each(|typ| typ.effect_holes(ctx, positive, rows)),
+            Self::VarApplication(fun, args) => {
+                fun.effect_holes(ctx, positive, rows);
+                args.iter()
+                    .for_each(|typ| typ.effect_holes(ctx, positive, rows));
+            }
+            Self::Forall(scheme) => scheme.mono.effect_holes(ctx, positive, rows),
+            Self::Record(fields, _) => fields
+                .iter()
+                .for_each(|(_, typ)| typ.effect_holes(ctx, positive, rows)),
+            _ => {}
+        }
+    }
+
+    /// The holes above the level of the context that appear in the types.
+    pub fn holes(types: &[Type], ctx: &Ctx) -> Vec<Ref> {
+        let mut holes = Vec::new();
+
+        for typ in types {
+            typ.variance(ctx, true, &mut holes);
+        }
+
+        holes.into_iter().map(|(hole, _)| hole).collect()
+    }
+
+    /// The holes above the level of the context that appear in positions of the types that are
+    /// not covariant, like the parameters of the arrows.
+    pub fn noncovariant_holes(types: &[Type], ctx: &Ctx) -> Vec<Ref> {
+        let mut holes = Vec::new();
+
+        for typ in types {
+            typ.variance(ctx, true, &mut holes);
+        }
+
+        holes
+            .into_iter()
+            .filter(|(_, covariant)| !covariant)
+            .map(|(hole, _)| hole)
+            .collect()
+    }
+
+    /// Lowers the holes to the level of the context, so they are not generalized by it.
+    pub fn lower_holes(holes: &[Ref], ctx: &Ctx) {
+        for hole in holes {
+            match hole.get() {
+                Hole::Empty(level) if level > ctx.level => {
+                    hole.get_item_mut().data = Hole::Empty(ctx.level);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Collects the holes above the level of the context, together with whether they are in a
+    /// covariant position. The holes in invariant positions are collected as both.
+    fn variance(self: &Type, ctx: &Ctx, covariant: bool, holes: &mut Vec<(Ref, bool)>) {
+        match &*self.clone().flatten() {
+            Self::Hole(hole) => match hole.get() {
+                Hole::Empty(level) if level > ctx.level => holes.push((hole.clone(), covariant)),
+                _ => {}
+            },
+            Self::Arrow(from, to, effects) => {
+                from.variance(ctx, !covariant, holes);
+                to.variance(ctx, covariant, holes);
+                effects.variance(ctx, covariant, holes);
+            }
+            // A reference can be read and written, so its type is invariant.
+            Self::Application(name, types) if name == REF => {
+                for typ in types {
+                    typ.variance(ctx, true, holes);
+                    typ.variance(ctx, false, holes);
+                }
+            }
+            Self::Tuple(types) | Self::Application(_, types) => types
+                .iter()
+                .for_each(|typ| typ.variance(ctx, covariant, holes)),
+            Self::VarApplication(fun, args) => {
+                fun.variance(ctx, covariant, holes);
+
+                for arg in args {
+                    arg.variance(ctx, true, holes);
+                    arg.variance(ctx, false, holes);
+                }
+            }
+            Self::Forall(scheme) => {
+                scheme.mono.variance(ctx, true, holes);
+                scheme.mono.variance(ctx, false, holes);
+            }
+            Self::Record(fields, rest) | Self::Effects(fields, rest) => {
+                for (_, typ) in fields {
+                    typ.variance(ctx, covariant, holes);
+                }
+
+                if let Some(rest) = rest {
+                    rest.variance(ctx, covariant, holes);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Replaces the holes of a level above the context by type variables, which are named in the
+    /// order that they appear.
     pub fn generalize(self: Type, ctx: Ctx) -> Rc<TypeScheme> {
-        let mut names = Default::default();
+        self.generalize_qualified(ctx, &[])
+    }
 
-        let mono = self.generalize_type(ctx, &mut names);
+    /// Generalizes a type together with the constraints on its holes.
+    pub fn generalize_qualified(self: Type, ctx: Ctx, predicates: &[Predicate]) -> Rc<TypeScheme> {
+        let mut holes = Vec::new();
 
-        Rc::new(TypeScheme {
-            names: names.into_values().collect(),
-            mono,
+        let mono = self.generalize_type(ctx.clone(), &mut holes);
+
+        let constraints = predicates
+            .iter()
+            .map(|predicate| Predicate {
+                class: predicate.class.clone(),
+                typ: predicate.typ.generalize_type(ctx.clone(), &mut holes),
+            })
+            .collect();
+
+        let names = holes.into_iter().map(|(_, name)| name).collect();
+        TypeScheme::qualified(names, constraints, mono)
+    }
+
+    /// The empty hole that a type is or that it applies, together with its level.
+    pub fn head_hole(self: &Type) -> Option<(Ref, usize)> {
+        match &*self.clone().flatten() {
+            Self::Hole(hole) => match hole.get() {
+                Hole::Empty(level) => Some((hole.clone(), level)),
+                Hole::Filled(_) => None,
+            },
+            Self::VarApplication(fun, _) => fun.head_hole(),
+            _ => None,
+        }
+    }
+
+    /// The fields of a record type, or the effects of a row of effects, including the ones of the records that fill its rows, sorted
+    /// by name, together with the row that stands for the other fields.
+    pub fn row(self: &Type) -> (Vec<(String, Type)>, Option<Type>) {
+        match &*self.clone().flatten() {
+            Self::Record(fields, rest) | Self::Effects(fields, rest) => record_row(fields, rest),
+            _ => (Vec::new(), Some(self.clone())),
+        }
+    }
+
+    pub fn contains_hole(self: &Type, hole: &Ref) -> bool {
+        match &*self.clone().flatten() {
+            Self::Hole(other) => other == hole,
+            Self::Tuple(types) | Self::Application(_, types) => {
+                types.iter().any(|typ| typ.contains_hole(hole))
+            }
+            Self::Arrow(from, to, effects) => {
+                from.contains_hole(hole) || to.contains_hole(hole) || effects.contains_hole(hole)
+            }
+            Self::VarApplication(fun, args) => {
+                fun.contains_hole(hole) || args.iter().any(|typ| typ.contains_hole(hole))
+            }
+            Self::Forall(scheme) => scheme.mono.contains_hole(hole),
+            Self::Record(fields, rest) | Self::Effects(fields, rest) => {
+                fields.iter().any(|(_, typ)| typ.contains_hole(hole))
+                    || rest.as_ref().is_some_and(|rest| rest.contains_hole(hole))
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks if two types are the same without unifying them.
+    pub fn is_same(self: &Type, other: &Type) -> bool {
+        let all_same = |left: &[Type], right: &[Type]| {
+            left.len() == right.len() && left.iter().zip(right).all(|(l, r)| l.is_same(r))
+        };
+
+        match (&*self.clone().flatten(), &*other.clone().flatten()) {
+            (Self::Var(left), Self::Var(right)) => left == right,
+            (Self::Hole(left), Self::Hole(right)) => left == right,
+            (Self::Skolem(_, left, _), Self::Skolem(_, right, _)) => left == right,
+            (Self::Tuple(left), Self::Tuple(right)) => all_same(left, right),
+            (Self::Arrow(from, to, effects), Self::Arrow(from1, to1, effects1)) => {
+                from.is_same(from1) && to.is_same(to1) && effects.is_same(effects1)
+            }
+            (Self::Application(name, args), Self::Application(name1, args1)) => {
+                name == name1 && all_same(args, args1)
+            }
+            (Self::VarApplication(fun, args), Self::VarApplication(fun1, args1)) => {
+                fun.is_same(fun1) && all_same(args, args1)
+            }
+            (Self::Record(..), Self::Record(..)) | (Self::Effects(..), Self::Effects(..)) => {
+                let (fields, rest) = self.row();
+                let (fields1, rest1) = other.row();
+
+                let same_fields = fields.len() == fields1.len()
+                    && fields
+                        .iter()
+                        .zip(&fields1)
+                        .all(|((name, typ), (name1, typ1))| name == name1 && typ.is_same(typ1));
+
+                let same_rest = match (rest, rest1) {
+                    (Some(rest), Some(rest1)) => rest.is_same(&rest1),
+                    (rest, rest1) => rest.is_none() && rest1.is_none(),
+                };
+
+                same_fields && same_rest
+            }
+            _ => false,
+        }
+    }
+}
+
+/// An effect of a row is shown without parenthesis, like `State Int`.
+pub fn effect_label(effect: &Type) -> String {
+    match &*effect.clone().flatten() {
+        MonoType::Application(name, args) if !args.is_empty() => {
+            format!("{name} {}", args.iter().join(" "))
+        }
+        _ => effect.to_string(),
+    }
+}
+
+/// The name of the type variable of an index, they are `a`, `b`, ..., `z`, `a1`, `b1` and so on.
+pub fn variable_name(index: usize) -> String {
+    let letter = (b'a' + (index % 26) as u8) as char;
+
+    match index / 26 {
+        0 => letter.to_string(),
+        round => format!("{letter}{round}"),
+    }
+}
+
+/// The names that a diagnostic gives to the empty holes of its types.
+///
+/// They are named by [variable_name] in the order that they appear, apart from the type variables
+/// and skolems of the types, so they are shown like type variables.
+pub struct Names {
+    reserved: HashSet<String>,
+    holes: Vec<(Ref, Type)>,
+}
+
+impl Names {
+    pub fn new(types: &[&Type]) -> Self {
+        let mut reserved = HashSet::new();
+
+        for typ in types {
+            typ.variables(&mut reserved);
+        }
+
+        Self {
+            reserved,
+            holes: Vec::new(),
+        }
+    }
+
+    /// Copies a type with the names in place of its holes. The holes of the rows of effects are
+    /// kept, because they are not shown.
+    pub fn name(&mut self, typ: &Type) -> Type {
+        typ.map_holes(&mut |hole| {
+            if hole.name() == EFFECT_HOLE {
+                return Rc::new(MonoType::Hole(hole.clone()));
+            }
+
+            if let Some((_, var)) = self.holes.iter().find(|(other, _)| other == hole) {
+                return var.clone();
+            }
+
+            let name = (0..)
+                .map(variable_name)
+                .filter(|name| !self.reserved.contains(name))
+                .nth(self.holes.len())
+                .unwrap();
+
+            let var = MonoType::var(name);
+            self.holes.push((hole.clone(), var.clone()));
+            var
         })
     }
+
+    pub fn predicate(&mut self, predicate: &Predicate) -> Predicate {
+        Predicate {
+            class: predicate.class.clone(),
+            typ: self.name(&predicate.typ),
+        }
+    }
+
+    pub fn scheme(&mut self, scheme: &TypeScheme) -> TypeScheme {
+        TypeScheme {
+            names: scheme.names.clone(),
+            constraints: scheme
+                .constraints
+                .iter()
+                .map(|predicate| self.predicate(predicate))
+                .collect(),
+            mono: self.name(&scheme.mono),
+        }
+    }
+}
+
+/// If a name is one of the names of the generalized rows of effects.
+fn is_row_name(name: &str) -> bool {
+    name.len() > 2 && name.starts_with("'e")
+}
+
+fn record_row(
+    fields: &[(String, Type)],
+    rest: &Option<Type>,
+) -> (Vec<(String, Type)>, Option<Type>) {
+    let Some((more, rest)) = rest.as_ref().map(MonoType::row) else {
+        return (fields.to_vec(), None);
+    };
+
+    let fields = fields.iter().cloned().merge_by(more, |l, r| l.0 <= r.0);
+    (fields.collect(), rest)
 }
 
 /// Is the signature of a constructor of a type, as an example, the signature of the constructor of
@@ -357,7 +1165,7 @@ impl ConstructorSignature {
         Self {
             name,
             args,
-            typ: TypeScheme { names, mono }.into(),
+            typ: TypeScheme::new(names, mono),
         }
     }
 }
@@ -375,7 +1183,34 @@ impl FunctionSignature {
     }
 
     pub fn return_type(&self) -> Type {
-        self.entire_type.mono.clone().flatten_back()
+        let mut typ = self.entire_type.mono.clone();
+
+        for _ in &self.args {
+            let MonoType::Arrow(_, to, _) = &*typ.clone().flatten() else {
+                break;
+            };
+
+            typ = to.clone();
+        }
+
+        typ
+    }
+
+    /// The effects that the body of the function can perform.
+    pub fn effects(&self) -> Type {
+        let mut typ = self.entire_type.mono.clone();
+        let mut effects = MonoType::pure();
+
+        for _ in &self.args {
+            let MonoType::Arrow(_, to, latent) = &*typ.clone().flatten() else {
+                break;
+            };
+
+            effects = latent.clone();
+            typ = to.clone();
+        }
+
+        effects
     }
 }
 
@@ -391,12 +1226,42 @@ impl Display for FunctionSignature {
     }
 }
 
+/// The signature of a method of a class, like `show : forall a. Show a => a -> String`.
+#[derive(Clone, Debug)]
+pub struct MethodSignature {
+    pub name: String,
+    pub class: String,
+    pub typ: Rc<TypeScheme>,
+}
+
+impl Display for MethodSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(method {} : {})", self.name, self.typ)
+    }
+}
+
+/// The signature of an operation of an effect, like `get : forall s. () -> s ! {State s}`.
+#[derive(Clone, Debug)]
+pub struct OperationSignature {
+    pub name: String,
+    pub effect: String,
+    pub typ: Rc<TypeScheme>,
+}
+
+impl Display for OperationSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(operation {} : {})", self.name, self.typ)
+    }
+}
+
 /// Is the signature of a function, as an example, the signature of the `map` function or the
 /// signature of a constructor like `Ok`.
 #[derive(Clone, Debug)]
 pub enum DeclSignature {
     Function(FunctionSignature),
     Constructor(Rc<ConstructorSignature>),
+    Method(Rc<MethodSignature>),
+    Operation(Rc<OperationSignature>),
 }
 
 impl Display for DeclSignature {
@@ -404,6 +1269,150 @@ impl Display for DeclSignature {
         match self {
             Self::Function(fs) => write!(f, "{}", fs),
             Self::Constructor(cs) => write!(f, "{}", cs),
+            Self::Method(ms) => write!(f, "{}", ms),
+            Self::Operation(os) => write!(f, "{}", os),
+        }
+    }
+}
+
+/// The signature of a class. Its methods are values that are polymorphic on the parameter of the
+/// class, and its instances are indexed by their type constructors, so they can't overlap.
+#[derive(Clone, Debug)]
+pub struct ClassSignature {
+    pub name: String,
+    pub param: String,
+    pub kind: Rc<Kind>,
+    pub methods: Vec<(String, Rc<TypeScheme>)>,
+    pub instances: im_rc::OrdMap<String, Rc<InstanceSignature>>,
+}
+
+impl Display for ClassSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "class {} {}", self.name, self.param)
+    }
+}
+
+/// An instance of a class for a type constructor applied to distinct type variables, like
+/// `Show (List a)`. The dictionaries of its constraints are needed to build its dictionary.
+#[derive(Clone, Debug)]
+pub struct InstanceSignature {
+    pub name: String,
+
+    /// The name of its dictionary, like `Show.List`.
+    pub symbol: String,
+    pub params: Vec<String>,
+    pub constraints: Vec<Predicate>,
+}
+
+/// The signature of an effect. Its operations are polymorphic on the parameters of the effect, and
+/// they are in the same order as in its declaration.
+#[derive(Clone, Debug)]
+pub struct EffectSignature {
+    pub name: String,
+    pub params: Vec<(String, Rc<Kind>)>,
+    pub operations: Vec<(String, Rc<TypeScheme>)>,
+}
+
+impl Display for EffectSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params = self.params.iter().map(|(x, _)| format!(" {x}")).join("");
+        write!(f, "effect {}{}", self.name, params)
+    }
+}
+
+/// The type of a type. The types of the values have the kind `*` and the type constructors have
+/// arrow kinds, like `* -> *` for `List`.
+#[derive(Debug)]
+pub enum Kind {
+    Star,
+    Arrow(Rc<Self>, Rc<Self>),
+
+    /// The kind of the rows of the record types, like the `r` of `{ name : String | r }`.
+    Row,
+
+    /// The kind of the rows of effects, like the `e` of `a -> b ! {State Int | e}`.
+    Effect,
+
+    /// A kind that is not known yet and is filled by the kind inference.
+    Hole(Rc<RefCell<Option<Rc<Self>>>>),
+}
+
+impl Kind {
+    pub fn star() -> Rc<Self> {
+        Rc::new(Self::Star)
+    }
+
+    pub fn row() -> Rc<Self> {
+        Rc::new(Self::Row)
+    }
+
+    pub fn effect() -> Rc<Self> {
+        Rc::new(Self::Effect)
+    }
+
+    pub fn new_hole() -> Rc<Self> {
+        Rc::new(Self::Hole(Default::default()))
+    }
+
+    pub fn arrow(self: Rc<Self>, to: Rc<Self>) -> Rc<Self> {
+        Rc::new(Self::Arrow(self, to))
+    }
+
+    /// Follows the filled holes until the kind that they stand for.
+    pub fn prune(self: &Rc<Self>) -> Rc<Self> {
+        match &**self {
+            Self::Hole(hole) => hole
+                .borrow()
+                .as_ref()
+                .map_or_else(|| self.clone(), |kind| kind.prune()),
+            _ => self.clone(),
+        }
+    }
+
+    /// The kinds of the parameters of a type constructor of this kind.
+    pub fn params(self: &Rc<Self>) -> Vec<Rc<Self>> {
+        match &*self.prune() {
+            Self::Arrow(param, result) => {
+                iter::once(param.clone()).chain(result.params()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Fills the holes that were not found by the kind inference with `*`.
+    pub fn default_holes(self: &Rc<Self>) {
+        match &**self {
+            Self::Star | Self::Row | Self::Effect => {}
+            Self::Arrow(param, result) => {
+                param.default_holes();
+                result.default_holes();
+            }
+            Self::Hole(hole) => {
+                let filled = hole.borrow().clone();
+
+                match filled {
+                    Some(kind) => kind.default_holes(),
+                    None => *hole.borrow_mut() = Some(Self::star()),
+                }
+            }
+        }
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Star => write!(f, "*"),
+            Self::Row => write!(f, "row"),
+            Self::Effect => write!(f, "effect"),
+            Self::Arrow(param, result) => match &*param.prune() {
+                Self::Arrow(..) => write!(f, "({}) -> {}", param, result),
+                _ => write!(f, "{} -> {}", param, result),
+            },
+            Self::Hole(hole) => match &*hole.borrow() {
+                Some(kind) => write!(f, "{}", kind),
+                None => write!(f, "_"),
+            },
         }
     }
 }
@@ -412,6 +1421,10 @@ impl Display for DeclSignature {
 pub enum TypeValue {
     Sum(Vec<Rc<ConstructorSignature>>),
     Product(Vec<(String, Type)>),
+
+    /// The expansion of an alias, where its parameters are type variables.
+    Alias(Type),
+
     Opaque,
 }
 
@@ -425,6 +1438,7 @@ pub enum TypeValue {
 pub struct TypeSignature {
     pub name: String,
     pub params: Vec<String>,
+    pub kind: Rc<Kind>,
     pub value: TypeValue,
 }
 
@@ -437,13 +1451,32 @@ impl TypeSignature {
     }
 
     pub fn new_opaque(name: String) -> Self {
+        Self::new_opaque_constructor(name, Vec::new())
+    }
+
+    /// An opaque type constructor, its parameters are types of values.
+    pub fn new_opaque_constructor(name: String, params: Vec<String>) -> Self {
+        let kind = params
+            .iter()
+            .fold(Kind::star(), |kind, _| Kind::star().arrow(kind));
+
         Self {
             name,
-            params: Vec::new(),
+            params,
+            kind,
             value: TypeValue::Opaque,
         }
     }
 
+    /// The parameters with their kinds, to be used as type variables.
+    pub fn type_variables(&self) -> im_rc::OrdMap<String, Rc<Kind>> {
+        self.params
+            .iter()
+            .cloned()
+            .zip(self.kind.params())
+            .collect()
+    }
+
     pub fn get_constructors(&self) -> Option<HashSet<String>> {
         match &self.value {
             TypeValue::Sum(constructors) => Some(
@@ -452,8 +1485,8 @@ impl TypeSignature {
                     .map(|x| x.name.clone())
                     .collect::<HashSet<_>>(),
             ),
-            TypeValue::Opaque => None,
-            TypeValue::Product(_) => todo!(),
+            TypeValue::Product(_) => Some(HashSet::from([self.name.clone()])),
+            TypeValue::Alias(_) | TypeValue::Opaque => None,
         }
     }
 
@@ -479,6 +1512,7 @@ impl Display for TypeValue {
                     .join("\n        ");
                 write!(f, "{{ {} }}", fields)
             }
+            Self::Alias(typ) => write!(f, "{}", typ),
             Self::Opaque => write!(f, "opaque"),
         }
     }
@@ -500,7 +1534,7 @@ impl Iterator for TypeIter {
 
     fn next(&mut self) -> Option<Self::Item> {
         match &*self.typ.clone().flatten() {
-            MonoType::Arrow(from, to) => {
+            MonoType::Arrow(from, to, _) => {
                 self.typ = to.clone();
                 Some(from.clone())
             }
@@ -1,19 +1,33 @@
 //! This module exposes functions like [unify] and [occur_check] for unification and occur checking
 //! that are useful for the checker to check if two types are equal. These functions produce side
 //! effects.
-use std::{fmt::Display, rc::Rc};
+//!
+//! The kinds of the types are unified by [unify_kinds].
+use std::{cell::RefCell, fmt::Display, rc::Rc};
+
+use itertools::{EitherOrBoth, Itertools};
 
 use crate::{
     context::Ctx,
-    types::{Hole, MonoType, Ref, Type},
+    types::{effect_label, Hole, Kind, MonoType, Names, Ref, Type},
 };
 
+/// The reasons for a hole to not be filled with a type.
 #[derive(Debug)]
-pub struct OccursCheck;
+pub enum OccursCheck {
+    /// The hole occurs inside of the type, so the type would be infinite.
+    Cycle,
+
+    /// The type has a skolem that is not in the scope of the hole.
+    Escape(String),
+}
 
 impl Display for OccursCheck {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "found cyclic type of infinite size")
+        match self {
+            Self::Cycle => write!(f, "found cyclic type of infinite size"),
+            Self::Escape(name) => write!(f, "the type variable '{name}' escapes its forall"),
+        }
     }
 }
 
@@ -21,22 +35,68 @@ pub struct TypeMismatch(Type, Type);
 
 impl Display for TypeMismatch {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "type mismatch between '{}' and '{}'", self.0, self.1)
+        let mut names = Names::new(&[&self.0, &self.1]);
+        let (left, right) = (names.name(&self.0), names.name(&self.1));
+        write!(f, "type mismatch between '{left}' and '{right}'")
+    }
+}
+
+/// A field that is required from a record type without it and without a row for other fields.
+pub struct MissingField(Type, String);
+
+impl Display for MissingField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let record = Names::new(&[&self.0]).name(&self.0);
+        write!(f, "the record type '{record}' has no field '{}'", self.1)
+    }
+}
+
+/// An effect that is performed where the row of effects doesn't have it and has no rest.
+pub struct MissingEffect(Type, Type);
+
+impl Display for MissingEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names = Names::new(&[&self.0, &self.1]);
+        let (effects, effect) = (names.name(&self.0), names.name(&self.1));
+        write!(
+            f,
+            "the effects '{effects}' don't include '{}'",
+            effect_label(&effect)
+        )
     }
 }
 
 /// Tries to find a general unifier for two types, it fails if these two types are not "equal".
 pub fn unify(ctx: Ctx, left: Type, right: Type) {
-    if Rc::ptr_eq(&left, &right) {
+    // Filled holes are compared by their content, so a hole is never unified with itself. The
+    // aliases are compared by their expansion, but they are kept for the holes and the errors.
+    let left = left.prune();
+    let right = right.prune();
+
+    let (left_exp, right_exp) = (left.clone().flatten(), right.clone().flatten());
+
+    if Rc::ptr_eq(&left_exp, &right_exp) {
         return;
     }
 
-    match (&*left, &*right) {
+    match (&*left_exp, &*right_exp) {
         (MonoType::Var(x), MonoType::Var(y)) if x == y => {}
 
-        (MonoType::Arrow(l, r), MonoType::Arrow(l1, r1)) => {
+        (MonoType::Skolem(_, x, _), MonoType::Skolem(_, y, _)) if x == y => {}
+
+        // Only the first mismatch is reported, the others are usually the same one, so after it
+        // the rest of the arrows only fill the holes.
+        (MonoType::Arrow(l, r, e), MonoType::Arrow(l1, r1, e1)) => {
+            let mut ctx = ctx;
+            let err_count = ctx.err_count();
             unify(ctx.clone(), l.clone(), l1.clone());
-            unify(ctx, r.clone(), r1.clone());
+
+            if ctx.err_count() != err_count {
+                ctx.errors = Default::default();
+            }
+
+            unify(ctx.clone(), r.clone(), r1.clone());
+            unify(ctx, e.clone(), e1.clone());
         }
 
         (MonoType::Hole(l), MonoType::Hole(r)) if l == r => {}
@@ -67,17 +127,222 @@ pub fn unify(ctx: Ctx, left: Type, right: Type) {
             }
         }
 
+        (MonoType::VarApplication(fun, args), MonoType::VarApplication(fun1, args1)) => {
+            unify_applications(ctx, (fun.clone(), args), (fun1.clone(), args1));
+        }
+
+        (MonoType::VarApplication(fun, args), MonoType::Application(name, args1))
+            if args.len() <= args1.len() =>
+        {
+            let fun1 = MonoType::typ(name.clone());
+            unify_applications(ctx, (fun.clone(), args), (fun1, args1));
+        }
+
+        (MonoType::Application(name, args), MonoType::VarApplication(fun1, args1))
+            if args1.len() <= args.len() =>
+        {
+            let fun = MonoType::typ(name.clone());
+            unify_applications(ctx, (fun, args), (fun1.clone(), args1));
+        }
+
+        // Two foralls are equal if their types are equal for the same skolems.
+        (MonoType::Forall(scheme), MonoType::Forall(scheme1))
+            if scheme.names.len() == scheme1.names.len() =>
+        {
+            let ctx = ctx.level_up();
+            let skolems = ctx.new_skolems(&scheme.names);
+
+            let left = scheme.instantiate_with(&skolems);
+            let right = scheme1.instantiate_with(&skolems);
+            unify(ctx, left, right);
+        }
+
+        (MonoType::Record(..), MonoType::Record(..))
+        | (MonoType::Effects(..), MonoType::Effects(..)) => unify_records(ctx, left, right),
+
         (MonoType::Error, _) | (_, MonoType::Error) => {}
 
         _ => ctx.dyn_error(TypeMismatch(left, right)),
     }
 }
 
+/// Unifies the fields that are in both records. The fields that are only in one of them must be
+/// in the row of the other one, so each row is unified with a record of the fields that it misses
+/// and a new row that is shared by both. The rows of effects are unified in the same way.
+fn unify_records(ctx: Ctx, left: Type, right: Type) {
+    let (fields, rest) = left.row();
+    let (fields1, rest1) = right.row();
+
+    let err_count = ctx.err_count();
+    let mut only_left = Vec::new();
+    let mut only_right = Vec::new();
+
+    for pair in fields
+        .into_iter()
+        .merge_join_by(fields1, |l, r| l.0.cmp(&r.0))
+    {
+        match pair {
+            EitherOrBoth::Both((_, l), (_, r)) => {
+                unify(ctx.clone(), l, r);
+
+                if ctx.err_count() > err_count {
+                    return;
+                }
+            }
+            EitherOrBoth::Left(field) => only_left.push(field),
+            EitherOrBoth::Right(field) => only_right.push(field),
+        }
+    }
+
+    let new_rest = match (&rest, &rest1) {
+        // A row can't have fields that are not in itself.
+        (Some(rest), Some(rest1))
+            if rest.is_same(rest1) && !(only_left.is_empty() && only_right.is_empty()) =>
+        {
+            return ctx.dyn_error(TypeMismatch(left, right));
+        }
+        (Some(rest), Some(rest1)) => {
+            let level = [rest, rest1]
+                .into_iter()
+                .filter_map(|rest| rest.head_hole())
+                .map(|(_, level)| level)
+                .min()
+                .unwrap_or(ctx.level);
+
+            match &*left.clone().flatten() {
+                MonoType::Effects(..) => Some(ctx.new_effect_hole_at(level)),
+                _ => Some(ctx.new_hole_at(level)),
+            }
+        }
+        _ => None,
+    };
+
+    unify_row(ctx.clone(), &left, rest, only_right, new_rest.clone());
+
+    if ctx.err_count() == err_count {
+        unify_row(ctx, &right, rest1, only_left, new_rest);
+    }
+}
+
+/// Unifies the row of a record with the fields that it misses and the rest of the row.
+fn unify_row(
+    ctx: Ctx,
+    record: &Type,
+    rest: Option<Type>,
+    missing: Vec<(String, Type)>,
+    new_rest: Option<Type>,
+) {
+    let is_effects = matches!(&*record.clone().flatten(), MonoType::Effects(..));
+
+    match (rest, new_rest) {
+        (Some(rest), Some(new_rest)) if missing.is_empty() => unify(ctx, rest, new_rest),
+        (Some(rest), new_rest) if is_effects => {
+            unify(ctx, rest, Rc::new(MonoType::Effects(missing, new_rest)));
+        }
+        (Some(rest), new_rest) => unify(ctx, rest, Rc::new(MonoType::Record(missing, new_rest))),
+        (None, _) => match missing.into_iter().next() {
+            Some((_, effect)) if is_effects => ctx.dyn_error(MissingEffect(record.clone(), effect)),
+            Some((name, _)) => ctx.dyn_error(MissingField(record.clone(), name)),
+            None => {}
+        },
+    }
+}
+
+/// Checks that an inferred type is at least as polymorphic as the expected one, the foralls of
+/// the expected type are skolemized and the ones of the inferred type are instantiated.
+pub fn subsume(ctx: Ctx, inferred: Type, expected: Type) {
+    let (inferred_exp, expected_exp) = (inferred.clone().flatten(), expected.clone().flatten());
+
+    match (&*inferred_exp, &*expected_exp) {
+        (_, MonoType::Forall(scheme)) => {
+            let ctx = ctx.level_up();
+            let expected = ctx.skolemize(scheme);
+            subsume(ctx, inferred, expected);
+        }
+
+        (MonoType::Forall(_), _) => {
+            let inferred = inferred.instantiate_forall(ctx.clone());
+            subsume(ctx, inferred, expected);
+        }
+
+        // The parameters are contravariant, so the expected one must be more polymorphic.
+        (MonoType::Arrow(from, to, effects), MonoType::Arrow(from1, to1, effects1))
+            if inferred_exp.is_higher_rank() || expected_exp.is_higher_rank() =>
+        {
+            let err_count = ctx.err_count();
+            subsume(ctx.clone(), from1.clone(), from.clone());
+
+            if ctx.err_count() == err_count {
+                subsume(ctx.clone(), to.clone(), to1.clone());
+            }
+
+            if ctx.err_count() == err_count {
+                unify(ctx, effects.clone(), effects1.clone());
+            }
+        }
+
+        _ => unify(ctx, inferred, expected),
+    }
+}
+
+/// Unifies the last arguments of two applications, the arguments that are left are applied to the
+/// head of the longest one, so `f a` is unified with `Result e a` by unifying `f` with `Result e`.
+fn unify_applications(ctx: Ctx, (fun, args): (Type, &[Type]), (fun1, args1): (Type, &[Type])) {
+    let len = args.len().min(args1.len());
+    let (init, args) = args.split_at(args.len() - len);
+    let (init1, args1) = args1.split_at(args1.len() - len);
+
+    let err_count = ctx.err_count();
+
+    let fun = MonoType::apply(fun, init.to_vec());
+    let fun1 = MonoType::apply(fun1, init1.to_vec());
+    unify(ctx.clone(), fun, fun1);
+
+    for (l, r) in args.iter().zip(args1) {
+        if ctx.err_count() > err_count {
+            return;
+        }
+        unify(ctx.clone(), l.clone(), r.clone());
+    }
+}
+
+/// Unifies two kinds, it fails if they are different or if a hole would contain itself.
+pub fn unify_kinds(left: &Rc<Kind>, right: &Rc<Kind>) -> bool {
+    let (left, right) = (left.prune(), right.prune());
+
+    match (&*left, &*right) {
+        (Kind::Star, Kind::Star) | (Kind::Row, Kind::Row) | (Kind::Effect, Kind::Effect) => true,
+        (Kind::Arrow(l, r), Kind::Arrow(l1, r1)) => unify_kinds(l, l1) && unify_kinds(r, r1),
+        (Kind::Hole(l), Kind::Hole(r)) if Rc::ptr_eq(l, r) => true,
+        (Kind::Hole(hole), _) => fill_kind(hole, right),
+        (_, Kind::Hole(hole)) => fill_kind(hole, left),
+        _ => false,
+    }
+}
+
+fn fill_kind(hole: &Rc<RefCell<Option<Rc<Kind>>>>, kind: Rc<Kind>) -> bool {
+    fn occurs(hole: &Rc<RefCell<Option<Rc<Kind>>>>, kind: &Rc<Kind>) -> bool {
+        match &*kind.prune() {
+            Kind::Star | Kind::Row | Kind::Effect => false,
+            Kind::Arrow(param, result) => occurs(hole, param) || occurs(hole, result),
+            Kind::Hole(other) => Rc::ptr_eq(hole, other),
+        }
+    }
+
+    let occurs = occurs(hole, &kind);
+
+    if !occurs {
+        *hole.borrow_mut() = Some(kind);
+    }
+
+    !occurs
+}
+
 /// This function unifies a hole with a type.
 fn unify_hole(ctx: Ctx, hole: &Ref, other: Type, swap: bool) {
     match hole.get() {
         Hole::Empty(lvl) => match occur_check(hole, lvl, other.clone()) {
-            Err(occurs_check) => ctx.error(occurs_check.to_string()),
+            Err(occurs_check) => ctx.dyn_error(occurs_check),
             Ok(_) => hole.fill(other),
         },
         Hole::Filled(filled) if swap => unify(ctx, other, filled),
@@ -96,7 +361,7 @@ fn occur_check(hole: &Ref, lvl: usize, other: Type) -> Result<(), OccursCheck> {
             }
         }
 
-        MonoType::Hole(other_hole) if hole == other_hole => return Err(OccursCheck),
+        MonoType::Hole(other_hole) if hole == other_hole => return Err(OccursCheck::Cycle),
 
         MonoType::Hole(other_hole) => match other_hole.get() {
             Hole::Empty(lvl2) => {
@@ -115,12 +380,45 @@ fn occur_check(hole: &Ref, lvl: usize, other: Type) -> Result<(), OccursCheck> {
             }
         }
 
-        MonoType::Arrow(l, r) => {
+        MonoType::Arrow(l, r, e) => {
             occur_check(hole, lvl, l.clone())?;
             occur_check(hole, lvl, r.clone())?;
+            occur_check(hole, lvl, e.clone())?;
+        }
+
+        MonoType::VarApplication(fun, args) => {
+            occur_check(hole, lvl, fun.clone())?;
+
+            for arg in args.clone() {
+                occur_check(hole, lvl, arg)?;
+            }
+        }
+
+        MonoType::Alias(_, args, typ) => {
+            for arg in args.clone() {
+                occur_check(hole, lvl, arg)?;
+            }
+
+            occur_check(hole, lvl, typ.clone())?;
+        }
+
+        MonoType::Forall(scheme) => occur_check(hole, lvl, scheme.mono.clone())?,
+
+        MonoType::Record(fields, rest) | MonoType::Effects(fields, rest) => {
+            for (_, typ) in fields {
+                occur_check(hole, lvl, typ.clone())?;
+            }
+
+            if let Some(rest) = rest {
+                occur_check(hole, lvl, rest.clone())?;
+            }
+        }
+
+        MonoType::Skolem(name, _, level) if *level > lvl => {
+            return Err(OccursCheck::Escape(name.clone()))
         }
 
-        MonoType::Var(_) => {}
+        MonoType::Var(_) | MonoType::Skolem(..) => {}
 
         MonoType::Error => {}
     }
@@ -8,7 +8,7 @@ use std::{path::PathBuf, process::exit};
 
 use atiny_checker::context::Ctx;
 use atiny_checker::infer::Infer;
-use atiny_parser::{error::from_lalrpop, ProgramParser};
+use atiny_parser::parse_program;
 use clap::Parser;
 
 #[derive(Parser)]
@@ -24,17 +24,18 @@ fn main() {
 
     let mut ctx = Ctx::default();
 
-    ProgramParser::new()
-        .parse(&code)
-        .map_err(|x| vec![from_lalrpop(x)])
-        .map(|parsed| parsed.infer(&mut ctx))
-        .map(|_| ctx.take_errors())
-        .and_then(|errs| errs.map_or_else(|| Ok(()), Err))
-        .unwrap_or_else(|errs| {
-            for err in errs {
-                eprint!("{}", err.with_code(&code, &file.to_string_lossy()));
-            }
-
-            exit(1)
-        });
+    // The healthy declarations are still type checked when there are syntax errors.
+    let (parsed, mut errs) = parse_program(&code);
+    parsed.infer(&mut ctx);
+    errs.extend(ctx.take_errors().unwrap_or_default());
+
+    let failed = errs.iter().any(|err| err.is_error());
+
+    for err in errs {
+        eprint!("{}", err.with_code(&code, &file.to_string_lossy()));
+    }
+
+    if failed {
+        exit(1)
+    }
 }
@@ -19,9 +19,26 @@ pub enum SugestionKind {
     Replace,
 }
 
+/// Errors stop the compilation, the other diagnostics are only shown.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Info,
+}
+
+impl Severity {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Info => "info",
+        }
+    }
+}
+
 pub struct Error {
     message: ErrorKind,
     location: ByteRange,
+    severity: Severity,
 }
 
 impl Error {
@@ -29,6 +46,7 @@ impl Error {
         Self {
             message: ErrorKind::Static(message),
             location,
+            severity: Severity::Error,
         }
     }
 
@@ -36,6 +54,7 @@ impl Error {
         Self {
             message: ErrorKind::Sugestion(message, kind),
             location,
+            severity: Severity::Error,
         }
     }
 
@@ -43,9 +62,18 @@ impl Error {
         Self {
             message: ErrorKind::Dynamic(Box::new(move || message.to_string())),
             location,
+            severity: Severity::Error,
         }
     }
 
+    pub fn with_severity(self, severity: Severity) -> Self {
+        Self { severity, ..self }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
     pub fn with_code<'a>(self, code: &'a str, file_name: &'a str) -> ErrorWithCode<'a> {
         ErrorWithCode {
             err: self,
@@ -70,12 +98,19 @@ fn write_lines(f: &mut Formatter<'_>, code: &str, line_start: usize, line_end: u
     Ok(())
 }
 
-fn write_err_header<'a>(f: &mut Formatter<'_>, a: impl IntoIterator<Item = &'a String>) -> Result {
+fn write_err_header<'a>(
+    f: &mut Formatter<'_>,
+    severity: Severity,
+    a: impl IntoIterator<Item = &'a str>,
+) -> Result {
+    let label = severity.label();
+    let pad = label.len() + 4;
+
     for (i, s) in a.into_iter().enumerate() {
         if i == 0 {
-            writeln!(f, "\n[error]: {s}\n")?;
+            writeln!(f, "\n[{label}]: {s}\n")?;
         } else {
-            writeln!(f, "         {s}\n")?;
+            writeln!(f, "{:pad$}{s}\n", "")?;
         }
     }
     Ok(())
@@ -84,7 +119,12 @@ fn write_err_header<'a>(f: &mut Formatter<'_>, a: impl IntoIterator<Item = &'a S
 impl<'a> Display for ErrorWithCode<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         let Self {
-            err: Error { message, location },
+            err:
+                Error {
+                    message,
+                    location,
+                    severity,
+                },
             code,
             file_name,
         } = self;
@@ -118,9 +158,14 @@ impl<'a> Display for ErrorWithCode<'a> {
 
             error => {
                 match error {
-                    ErrorKind::Static(Message::Single(s)) => write_err_header(f, Some(s))?,
-                    ErrorKind::Static(Message::Multi(m)) => write_err_header(f, m)?,
-                    ErrorKind::Dynamic(d) => write_err_header(f, Some(&d()))?,
+                    ErrorKind::Static(Message::Single(s)) => {
+                        write_err_header(f, *severity, Some(s.as_str()))?;
+                    }
+                    ErrorKind::Static(Message::Multi(m)) => {
+                        write_err_header(f, *severity, m.iter().map(String::as_str))?;
+                    }
+                    // The lines of a dynamic message are shown like the ones of a multi message.
+                    ErrorKind::Dynamic(d) => write_err_header(f, *severity, d().lines())?,
                     ErrorKind::Sugestion(..) => unreachable!(),
                 };
 
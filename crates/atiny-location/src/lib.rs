@@ -13,8 +13,11 @@ pub struct Byte(pub usize);
 impl Byte {
     /// Discovers a [Point] that is a line and column structure using the index inside the source
     /// code. Probably it's an expensive operation so I guess we will change it in the future.
+    /// Positions after the last line, like the end of a file that ends with a new line, are placed
+    /// at the end of the last line.
     pub fn locate(&self, code: &str) -> Point {
         let mut acc = 0;
+        let mut last = Point::default();
         for (line, code_line) in code.lines().enumerate() {
             if acc + code_line.len() + 1 > self.0 {
                 return Point {
@@ -23,8 +26,12 @@ impl Byte {
                 };
             }
             acc += code_line.len() + 1;
+            last = Point {
+                line,
+                column: code_line.len(),
+            };
         }
-        Point::default()
+        last
     }
 }
 
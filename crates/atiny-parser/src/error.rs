@@ -1,22 +1,137 @@
+use std::fmt::{self, Display};
+
 use atiny_error::Error;
-use atiny_location::ByteRange;
-
-use lalrpop_util::{lexer::Token, ParseError};
-
-pub fn from_lalrpop(err: ParseError<usize, Token, &str>) -> Error {
-    use ParseError::*;
-    match err {
-        InvalidToken { location } => error("Invalid token", location),
-        UnrecognizedEof { location, .. } => error("unrecognized eof", location),
-        UnrecognizedToken { token, .. } => error("unrecognized token", token.0),
-        ExtraToken { token } => error("extra token", token.0),
-        User { .. } => panic!("oh no :>"),
+use atiny_location::{Byte, ByteRange};
+
+use crate::lexer::Token;
+
+/// Something that the parser would accept in the place of an unexpected token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expected {
+    Token(Token<'static>),
+    LowerId,
+    UpperId,
+    Expression,
+    Pattern,
+    Type,
+    Operator,
+    Precedence,
+    Declaration,
+}
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Token(token) => write!(f, "`{token}`"),
+            Self::LowerId => write!(f, "a lowercase identifier"),
+            Self::UpperId => write!(f, "an uppercase identifier"),
+            Self::Expression => write!(f, "an expression"),
+            Self::Pattern => write!(f, "a pattern"),
+            Self::Type => write!(f, "a type"),
+            Self::Operator => write!(f, "an operator"),
+            Self::Precedence => write!(f, "a precedence"),
+            Self::Declaration => write!(f, "a declaration"),
+        }
+    }
+}
+
+/// Errors that can happen while lexing or parsing a source code.
+#[derive(Debug)]
+pub enum SyntaxError {
+    InvalidToken(char, ByteRange),
+    NumberTooLarge(ByteRange),
+    UnterminatedComment(ByteRange),
+    UnterminatedString(ByteRange),
+    UnterminatedChar(ByteRange),
+    InvalidCharLiteral(ByteRange),
+    InvalidEscape(ByteRange),
+    InvalidPrecedence(ByteRange),
+    DuplicatedFixity(String, ByteRange),
+    AmbiguousOperators(String, String, ByteRange),
+    UnrecognizedEof(Byte, Vec<Expected>),
+    UnrecognizedToken(String, ByteRange, Vec<Expected>),
+}
+
+impl SyntaxError {
+    pub fn location(&self) -> ByteRange {
+        use SyntaxError::*;
+        match self {
+            UnrecognizedEof(location, _) => ByteRange(*location, *location),
+            InvalidToken(_, location)
+            | NumberTooLarge(location)
+            | UnterminatedComment(location)
+            | UnterminatedString(location)
+            | UnterminatedChar(location)
+            | InvalidCharLiteral(location)
+            | InvalidEscape(location)
+            | InvalidPrecedence(location)
+            | DuplicatedFixity(_, location)
+            | AmbiguousOperators(_, _, location)
+            | UnrecognizedToken(_, location, _) => *location,
+        }
+    }
+}
+
+impl From<SyntaxError> for Error {
+    fn from(err: SyntaxError) -> Self {
+        use SyntaxError::*;
+        let location = err.location();
+
+        match err {
+            InvalidToken(char, _) => error(format!("invalid character `{char}`"), location),
+            NumberTooLarge(_) => error("number literal is too large".to_string(), location),
+            UnterminatedComment(_) => error("unterminated block comment".to_string(), location),
+            UnterminatedString(_) => error("unterminated string literal".to_string(), location),
+            UnterminatedChar(_) => error("unterminated char literal".to_string(), location),
+            InvalidCharLiteral(_) => error(
+                "char literals must contain exactly one character".to_string(),
+                location,
+            ),
+            InvalidEscape(_) => error("invalid escape sequence".to_string(), location),
+            InvalidPrecedence(_) => error(
+                "the precedence of an operator must be between 0 and 9".to_string(),
+                location,
+            ),
+            DuplicatedFixity(operator, _) => error(
+                format!("the fixity of `{operator}` is declared more than once"),
+                location,
+            ),
+            AmbiguousOperators(left, right, _) => error(
+                format!("cannot mix {left} and {right} in the same infix expression"),
+                location,
+            ),
+            UnrecognizedEof(_, expected) => error(unexpected("end of file", &expected), location),
+            UnrecognizedToken(token, _, expected) => {
+                error(unexpected(&format!("`{token}`"), &expected), location)
+            }
+        }
     }
 }
 
-fn error(error: &str, singleton: usize) -> Error {
-    Error::new(
-        atiny_error::Message::Single(error.to_owned()),
-        ByteRange::singleton(singleton),
-    )
+/// Builds a message like "unexpected `x`, expected `=>` or a pattern".
+fn unexpected(found: &str, expected: &[Expected]) -> String {
+    let mut message = format!("unexpected {found}");
+
+    if let Some((last, init)) = expected.split_last() {
+        message.push_str(", expected ");
+
+        for (i, item) in init.iter().enumerate() {
+            if i > 0 {
+                message.push_str(", ");
+            }
+            message.push_str(&item.to_string());
+        }
+
+        if !init.is_empty() {
+            message.push_str(" or ");
+        }
+
+        message.push_str(&last.to_string());
+    }
+
+    message
+}
+
+fn error(error: String, location: ByteRange) -> Error {
+    Error::new(atiny_error::Message::Single(error), location)
 }
@@ -0,0 +1,256 @@
+//! Resolves the [ExprKind::Operators] sequences produced by the parser into applications, using the
+//! fixity of the built-in operators and the ones declared with `infixl`, `infixr` and `infix`.
+//!
+//! The sequences are resolved by precedence climbing. Operators without a declared fixity are left
+//! associative with the [MAX_PRECEDENCE].
+
+use std::collections::{HashMap, HashSet};
+use std::iter::Peekable;
+
+use atiny_location::{ByteRange, Located};
+use atiny_tree::r#abstract::*;
+
+use crate::error::SyntaxError;
+
+pub const MAX_PRECEDENCE: u8 = 9;
+
+/// Built-in infix operators with their fixity and the prelude function or constructor they
+/// desugar into.
+const BUILTINS: &[(&str, Associativity, u8, &str)] = &[
+    (ASSIGN, Associativity::None, 1, ASSIGN),
+    ("||", Associativity::Left, 2, "or"),
+    ("&&", Associativity::Left, 3, "and"),
+    ("==", Associativity::Left, 4, "eq"),
+    ("!=", Associativity::Left, 4, "neq"),
+    ("<", Associativity::Left, 4, "lt"),
+    ("<=", Associativity::Left, 4, "le"),
+    (">", Associativity::Left, 4, "gt"),
+    (">=", Associativity::Left, 4, "ge"),
+    ("::", Associativity::Right, 5, "Cons"),
+    ("+", Associativity::Left, 6, "add"),
+    ("-", Associativity::Left, 6, "sub"),
+    ("*", Associativity::Left, 7, "mul"),
+    ("/", Associativity::Left, 7, "div"),
+];
+
+/// Prefix operators and the prelude function they desugar into.
+pub const PREFIX: &[(&str, &str)] = &[("-", "neg")];
+
+/// The prefix operator that reads a reference, it's parsed into [ExprKind::Deref]. The booleans
+/// are negated by the prelude function `not` instead.
+pub const DEREF: &str = "!";
+
+/// The operator that writes to a reference, it's resolved into [ExprKind::Assign].
+pub const ASSIGN: &str = ":=";
+
+/// The name of the function that is called by an operator. User defined operators are functions
+/// with the operator itself as name.
+pub fn function_name(operator: &str) -> String {
+    BUILTINS
+        .iter()
+        .map(|(builtin, _, _, name)| (builtin, name))
+        .chain(PREFIX.iter().map(|(prefix, name)| (prefix, name)))
+        .find(|(symbol, _)| **symbol == operator)
+        .map_or(operator, |(_, name)| name)
+        .to_string()
+}
+
+#[derive(Clone, Copy)]
+struct Fixity {
+    associativity: Associativity,
+    precedence: u8,
+}
+
+impl Fixity {
+    fn describe(self, operator: &str) -> String {
+        let keyword = self.associativity.keyword();
+        format!("`{operator}` ({keyword} {})", self.precedence)
+    }
+}
+
+pub struct Resolver {
+    fixities: HashMap<String, Fixity>,
+    pub errors: Vec<SyntaxError>,
+}
+
+impl Resolver {
+    /// Creates a resolver with the built-in operators and the fixities declared in `program`.
+    pub fn new(program: &[TopLevel]) -> Self {
+        let mut fixities = HashMap::new();
+        let mut errors = Vec::new();
+
+        for (operator, associativity, precedence, _) in BUILTINS {
+            let fixity = Fixity {
+                associativity: *associativity,
+                precedence: *precedence,
+            };
+            fixities.insert(operator.to_string(), fixity);
+        }
+
+        let mut declared = HashSet::new();
+
+        for top_level in program {
+            if let TopLevelKind::Fixity(decl) = &top_level.data {
+                let operator = &decl.operator;
+
+                if !declared.insert(operator.data.clone()) {
+                    let error =
+                        SyntaxError::DuplicatedFixity(operator.data.clone(), operator.location);
+                    errors.push(error);
+                    continue;
+                }
+
+                let fixity = Fixity {
+                    associativity: decl.associativity,
+                    precedence: decl.precedence,
+                };
+                fixities.insert(operator.data.clone(), fixity);
+            }
+        }
+
+        Self { fixities, errors }
+    }
+
+    fn fixity(&self, operator: &str) -> Fixity {
+        self.fixities.get(operator).copied().unwrap_or(Fixity {
+            associativity: Associativity::Left,
+            precedence: MAX_PRECEDENCE,
+        })
+    }
+
+    pub fn program(&mut self, program: &mut [TopLevel]) {
+        for top_level in program {
+            match &mut top_level.data {
+                TopLevelKind::FnDecl(decl) => self.expr(&mut decl.body),
+                TopLevelKind::Instance(decl) => decl
+                    .methods
+                    .iter_mut()
+                    .for_each(|method| self.expr(&mut method.body)),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn expr(&mut self, expr: &mut Expr) {
+        match &mut expr.data {
+            ExprKind::Atom(AtomKind::Tuple(exprs)) => {
+                exprs.iter_mut().for_each(|expr| self.expr(expr));
+            }
+            ExprKind::Atom(_) | ExprKind::Hole(_) | ExprKind::Error => {}
+            ExprKind::Match(scrutinee, clauses) => {
+                self.expr(scrutinee);
+                for clause in clauses {
+                    if let Some(guard) = &mut clause.guard {
+                        self.expr(guard);
+                    }
+                    self.expr(&mut clause.expr);
+                }
+            }
+            ExprKind::Abstraction(_, expr)
+            | ExprKind::Annotation(expr, _)
+            | ExprKind::Field(expr, _)
+            | ExprKind::Ref(expr)
+            | ExprKind::Deref(expr) => self.expr(expr),
+            ExprKind::Application(fun, arg) | ExprKind::Assign(fun, arg) => {
+                self.expr(fun);
+                self.expr(arg);
+            }
+            ExprKind::RecordCreation(expr, fields) => {
+                self.expr(expr);
+                fields
+                    .iter_mut()
+                    .for_each(|field| self.expr(&mut field.expr));
+            }
+            ExprKind::Record(fields) => fields
+                .iter_mut()
+                .for_each(|field| self.expr(&mut field.expr)),
+            ExprKind::Handle(expr, clauses) => {
+                self.expr(expr);
+                clauses
+                    .iter_mut()
+                    .for_each(|clause| self.expr(&mut clause.body));
+            }
+            ExprKind::Block(statements) => {
+                for statement in statements {
+                    match &mut statement.data {
+                        StatementKind::Let(_, expr) | StatementKind::Expr(expr) => self.expr(expr),
+                        StatementKind::LetRec(bindings) => bindings
+                            .iter_mut()
+                            .for_each(|binding| self.expr(&mut binding.value)),
+                    }
+                }
+            }
+            ExprKind::Operators(..) => {
+                let ExprKind::Operators(mut operands, operators) =
+                    std::mem::replace(&mut expr.data, ExprKind::Error)
+                else {
+                    unreachable!()
+                };
+
+                operands.iter_mut().for_each(|operand| self.expr(operand));
+
+                let mut operands = operands.into_iter();
+                let mut operators = operators.into_iter().peekable();
+
+                // The sequence keeps its own location, which covers the enclosing parentheses.
+                let first = operands.next().unwrap();
+                expr.data = self.climb(first, 0, &mut operands, &mut operators).data;
+            }
+        }
+    }
+
+    /// Applies all the operators with at least the `min` precedence that follow `left`.
+    fn climb<E, O>(
+        &mut self,
+        mut left: Expr,
+        min: u8,
+        operands: &mut E,
+        operators: &mut Peekable<O>,
+    ) -> Expr
+    where
+        E: Iterator<Item = Expr>,
+        O: Iterator<Item = Located<String>>,
+    {
+        while let Some(operator) = operators.next_if(|op| self.fixity(&op.data).precedence >= min) {
+            let fixity = self.fixity(&operator.data);
+            let mut right = operands.next().unwrap();
+
+            while let Some(next) = operators.peek() {
+                let next_fixity = self.fixity(&next.data);
+
+                let min = if next_fixity.precedence > fixity.precedence {
+                    fixity.precedence + 1
+                } else if next_fixity.precedence < fixity.precedence {
+                    break;
+                } else {
+                    match (fixity.associativity, next_fixity.associativity) {
+                        (Associativity::Right, Associativity::Right) => fixity.precedence,
+                        (Associativity::Left, Associativity::Left) => break,
+                        _ => {
+                            let left = fixity.describe(&operator.data);
+                            let right = next_fixity.describe(&next.data);
+                            let error = SyntaxError::AmbiguousOperators(left, right, next.location);
+                            self.errors.push(error);
+                            break;
+                        }
+                    }
+                };
+
+                right = self.climb(right, min, operands, operators);
+            }
+
+            let location = ByteRange(left.location.0, right.location.1);
+
+            let data = if operator.data == ASSIGN {
+                ExprKind::Assign(Box::new(left), Box::new(right))
+            } else {
+                let operator = operator.map(|operator| function_name(&operator));
+                ExprKind::infix(left, operator, right)
+            };
+
+            left = Located::new(location, data);
+        }
+
+        left
+    }
+}
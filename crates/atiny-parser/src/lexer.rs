@@ -0,0 +1,401 @@
+//! This module transforms a source code into a sequence of [Token]s. Whitespace and comments are
+//! skipped and every token carries the [ByteRange] where it was found in the source.
+//!
+//! Line comments start with `//` and block comments are delimited by `/*` and `*/`, which can be
+//! nested. Documentation comments start with `///` and are kept as [Token::DocComment].
+//!
+//! Operators are sequences of the symbols in [OPERATOR_SYMBOLS], except for the few sequences
+//! that are reserved by the syntax like `|`, `=` and `->`. A `?` followed by a name is a typed hole,
+//! like `?todo`. A `!` before another one is lexed alone, so dereferences can be nested like `!!r`.
+//!
+//! String and char literals accept the escapes `\n`, `\t`, `\r`, `\0`, `\\`, `\"`, `\'` and `\u{7FFF}`.
+//! Invalid characters are reported and skipped so the lexing never stops before the end.
+
+use std::fmt::{self, Display};
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use atiny_location::{Byte, ByteRange};
+use atiny_tree::r#abstract::Associativity;
+
+use crate::error::SyntaxError;
+
+/// The smallest unit of the atiny syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token<'a> {
+    LBrace,
+    RBrace,
+    LPar,
+    RPar,
+    LBracket,
+    RBracket,
+    Comma,
+    Semi,
+    Wildcard,
+    Bar,
+    Dot,
+    DotDot,
+    Colon,
+    Equal,
+    FatArrow,
+    Arrow,
+
+    If,
+    Let,
+    Rec,
+    Else,
+    Match,
+    Forall,
+    Type,
+    Fn,
+    Class,
+    Instance,
+    Where,
+    Effect,
+    Handle,
+    Ref,
+
+    Num(u64),
+    Str(String),
+    Char(char),
+    LowerId(&'a str),
+    UpperId(&'a str),
+    Operator(&'a str),
+    Hole(&'a str),
+    DocComment(&'a str),
+}
+
+impl Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LBrace => write!(f, "{{"),
+            Self::RBrace => write!(f, "}}"),
+            Self::LPar => write!(f, "("),
+            Self::RPar => write!(f, ")"),
+            Self::LBracket => write!(f, "["),
+            Self::RBracket => write!(f, "]"),
+            Self::Comma => write!(f, ","),
+            Self::Semi => write!(f, ";"),
+            Self::Wildcard => write!(f, "_"),
+            Self::Bar => write!(f, "|"),
+            Self::Dot => write!(f, "."),
+            Self::DotDot => write!(f, ".."),
+            Self::Colon => write!(f, ":"),
+            Self::Equal => write!(f, "="),
+            Self::FatArrow => write!(f, "=>"),
+            Self::Arrow => write!(f, "->"),
+            Self::If => write!(f, "if"),
+            Self::Let => write!(f, "let"),
+            Self::Rec => write!(f, "rec"),
+            Self::Else => write!(f, "else"),
+            Self::Match => write!(f, "match"),
+            Self::Forall => write!(f, "forall"),
+            Self::Type => write!(f, "type"),
+            Self::Fn => write!(f, "fn"),
+            Self::Class => write!(f, "class"),
+            Self::Instance => write!(f, "instance"),
+            Self::Where => write!(f, "where"),
+            Self::Effect => write!(f, "effect"),
+            Self::Handle => write!(f, "handle"),
+            Self::Ref => write!(f, "ref"),
+            Self::Num(n) => write!(f, "{n}"),
+            Self::Str(s) => write!(f, "{s:?}"),
+            Self::Char(c) => write!(f, "{c:?}"),
+            Self::LowerId(id) | Self::UpperId(id) | Self::Operator(id) => write!(f, "{id}"),
+            Self::Hole(name) => write!(f, "?{name}"),
+            Self::DocComment(doc) => write!(f, "///{doc}"),
+        }
+    }
+}
+
+impl Token<'_> {
+    /// Tokens that start a top level declaration, they are used to recover from syntax errors. The
+    /// fixity declarations are recognized by [Token::fixity] instead.
+    pub const fn is_declaration_start(&self) -> bool {
+        matches!(
+            self,
+            Self::Fn | Self::Type | Self::Class | Self::Instance | Self::Effect
+        )
+    }
+
+    /// The associativity declared by a fixity keyword. `infixl`, `infixr` and `infix` are only
+    /// keywords at the start of a top level declaration, so they are lexed as identifiers.
+    pub fn fixity(&self) -> Option<Associativity> {
+        match self {
+            Self::LowerId("infixl") => Some(Associativity::Left),
+            Self::LowerId("infixr") => Some(Associativity::Right),
+            Self::LowerId("infix") => Some(Associativity::None),
+            _ => None,
+        }
+    }
+}
+
+/// The symbols that can be combined into operators.
+pub const OPERATOR_SYMBOLS: &str = "!$%&*+-./:<=>?@^|~";
+
+/// A token together with its location.
+pub type Spanned<'a> = (Token<'a>, ByteRange);
+
+/// An iterator over the [Token]s of a source code.
+pub struct Lexer<'a> {
+    code: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    pub errors: Vec<SyntaxError>,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(code: &'a str) -> Self {
+        Self {
+            code,
+            chars: code.char_indices().peekable(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn offset(&mut self) -> usize {
+        self.chars.peek().map_or(self.code.len(), |(i, _)| *i)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn accumulate(&mut self, start: usize, pred: impl Fn(char) -> bool) -> &'a str {
+        while self.chars.next_if(|(_, c)| pred(*c)).is_some() {}
+        &self.code[start..self.offset()]
+    }
+
+    fn identifier(&mut self, start: usize) -> Token<'a> {
+        let id = self.accumulate(start, |c| c.is_ascii_alphanumeric() || c == '_');
+
+        match id {
+            "_" => Token::Wildcard,
+            "if" => Token::If,
+            "let" => Token::Let,
+            "rec" => Token::Rec,
+            "else" => Token::Else,
+            "match" => Token::Match,
+            "forall" => Token::Forall,
+            "type" => Token::Type,
+            "fn" => Token::Fn,
+            "class" => Token::Class,
+            "instance" => Token::Instance,
+            "where" => Token::Where,
+            "effect" => Token::Effect,
+            "handle" => Token::Handle,
+            "ref" => Token::Ref,
+            _ if id.starts_with(|c: char| c.is_ascii_uppercase()) => Token::UpperId(id),
+            _ => Token::LowerId(id),
+        }
+    }
+
+    fn single(&mut self, token: Token<'a>) -> Token<'a> {
+        self.chars.next();
+        token
+    }
+
+    fn is_comment_start(&self, start: usize) -> bool {
+        self.code[start..].starts_with("//") || self.code[start..].starts_with("/*")
+    }
+
+    /// Lexes a comment. Returns [None] for the comments that are not documentation comments.
+    fn comment(&mut self, start: usize) -> Option<Token<'a>> {
+        self.chars.next();
+
+        if self.chars.next_if(|(_, c)| *c == '*').is_some() {
+            self.block_comment(start);
+            return None;
+        }
+
+        self.chars.next();
+
+        // Only exactly three slashes start a documentation comment, so `////` is a line comment.
+        let doc = self.chars.next_if(|(_, c)| *c == '/').is_some()
+            && !matches!(self.chars.peek(), Some((_, '/')));
+
+        let from = self.offset();
+        let text = self.accumulate(from, |c| c != '\n');
+
+        doc.then(|| Token::DocComment(text.trim_end()))
+    }
+
+    fn block_comment(&mut self, start: usize) {
+        let mut depth = 1;
+
+        while depth > 0 {
+            match self.chars.next() {
+                Some((_, '/')) if self.chars.next_if(|(_, c)| *c == '*').is_some() => depth += 1,
+                Some((_, '*')) if self.chars.next_if(|(_, c)| *c == '/').is_some() => depth -= 1,
+                Some(_) => {}
+                None => {
+                    let location = ByteRange(Byte(start), Byte(start + 2));
+                    self.errors.push(SyntaxError::UnterminatedComment(location));
+                    return;
+                }
+            }
+        }
+    }
+
+    fn string(&mut self, start: usize) -> Token<'a> {
+        self.chars.next();
+        let mut string = String::new();
+
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => break,
+                Some((i, '\\')) => string.extend(self.escape(i)),
+                Some((_, c)) => string.push(c),
+                None => {
+                    let location = ByteRange(Byte(start), Byte(start + 1));
+                    self.errors.push(SyntaxError::UnterminatedString(location));
+                    break;
+                }
+            }
+        }
+
+        Token::Str(string)
+    }
+
+    fn char(&mut self, start: usize) -> Token<'a> {
+        self.chars.next();
+        let errors = self.errors.len();
+
+        let char = match self.chars.next_if(|(_, c)| *c != '\'' && *c != '\n') {
+            Some((i, '\\')) => self.escape(i),
+            Some((_, c)) => Some(c),
+            None => None,
+        };
+
+        // A literal with more characters is skipped until its closing quote in the same line.
+        let from = self.offset();
+        let extra = self.accumulate(from, |c| c != '\'' && c != '\n');
+        let closed = self.chars.next_if(|(_, c)| *c == '\'').is_some();
+        let location = ByteRange(Byte(start), Byte(self.offset()));
+
+        if !closed {
+            self.errors.push(SyntaxError::UnterminatedChar(location));
+        } else if (char.is_none() && errors == self.errors.len()) || !extra.is_empty() {
+            self.errors.push(SyntaxError::InvalidCharLiteral(location));
+        }
+
+        Token::Char(char.unwrap_or_default())
+    }
+
+    /// Lexes the escape sequence that starts with the `\\` at `start`.
+    fn escape(&mut self, start: usize) -> Option<char> {
+        let char = match self.chars.next() {
+            Some((_, 'n')) => Some('\n'),
+            Some((_, 't')) => Some('\t'),
+            Some((_, 'r')) => Some('\r'),
+            Some((_, '0')) => Some('\0'),
+            Some((_, c @ ('\\' | '"' | '\''))) => Some(c),
+            Some((_, 'u')) => self.unicode_escape(),
+            _ => None,
+        };
+
+        if char.is_none() {
+            let location = ByteRange(Byte(start), Byte(self.offset()));
+            self.errors.push(SyntaxError::InvalidEscape(location));
+        }
+
+        char
+    }
+
+    fn unicode_escape(&mut self) -> Option<char> {
+        self.chars.next_if(|(_, c)| *c == '{')?;
+        let from = self.offset();
+        let digits = self.accumulate(from, |c| c.is_ascii_hexdigit());
+        self.chars.next_if(|(_, c)| *c == '}')?;
+        u32::from_str_radix(digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+    }
+
+    fn operator(&mut self, start: usize) -> Token<'a> {
+        // A `!` before another one is a dereference on its own, so `!!r` reads twice.
+        if self.code[start..].starts_with("!!") {
+            self.chars.next();
+            return Token::Operator("!");
+        }
+
+        // An operator ends at the start of a comment, so `a +// comment` is still an addition.
+        while let Some(&(i, c)) = self.chars.peek() {
+            if !OPERATOR_SYMBOLS.contains(c) || self.is_comment_start(i) {
+                break;
+            }
+            self.chars.next();
+        }
+
+        match &self.code[start..self.offset()] {
+            "?" if self
+                .chars
+                .peek()
+                .is_some_and(|(_, c)| c.is_ascii_lowercase()) =>
+            {
+                let from = self.offset();
+                Token::Hole(self.accumulate(from, |c| c.is_ascii_alphanumeric() || c == '_'))
+            }
+            "|" => Token::Bar,
+            "." => Token::Dot,
+            ".." => Token::DotDot,
+            ":" => Token::Colon,
+            "=" => Token::Equal,
+            "=>" => Token::FatArrow,
+            "->" => Token::Arrow,
+            operator => Token::Operator(operator),
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Spanned<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.skip_whitespace();
+
+            let (start, char) = *self.chars.peek()?;
+
+            let token = match char {
+                '{' => self.single(Token::LBrace),
+                '}' => self.single(Token::RBrace),
+                '(' => self.single(Token::LPar),
+                ')' => self.single(Token::RPar),
+                '[' => self.single(Token::LBracket),
+                ']' => self.single(Token::RBracket),
+                ',' => self.single(Token::Comma),
+                ';' => self.single(Token::Semi),
+                '/' if self.is_comment_start(start) => match self.comment(start) {
+                    Some(token) => token,
+                    None => continue,
+                },
+
+                '"' => self.string(start),
+                '\'' => self.char(start),
+
+                '0'..='9' => {
+                    let num = self.accumulate(start, |c| c.is_ascii_digit());
+                    let location = ByteRange(Byte(start), Byte(self.offset()));
+
+                    Token::Num(num.parse().unwrap_or_else(|_| {
+                        self.errors.push(SyntaxError::NumberTooLarge(location));
+                        u64::MAX
+                    }))
+                }
+
+                'a'..='z' | 'A'..='Z' | '_' => self.identifier(start),
+
+                _ if OPERATOR_SYMBOLS.contains(char) => self.operator(start),
+
+                _ => {
+                    self.chars.next();
+                    let location = ByteRange(Byte(start), Byte(self.offset()));
+                    self.errors.push(SyntaxError::InvalidToken(char, location));
+                    continue;
+                }
+            };
+
+            return Some((token, ByteRange(Byte(start), Byte(self.offset()))));
+        }
+    }
+}
@@ -1,16 +1,10 @@
 //! This module parses a text source code into a [atiny_tree::abstract] tree. Following the spec
-//! that is described in the README.md and using a LALR(1) parser generator called LALRPOP. It does
-//! not include error recovery strategies nor incremental parsing.
+//! that is described in the README.md and using a hand-written lexer and recursive descent parser.
+//! It recovers from syntax errors but it does not include incremental parsing.
 
 pub mod error;
+pub mod fixity;
+pub mod lexer;
+pub mod parser;
 
-#[macro_use]
-extern crate lalrpop_util;
-
-lalrpop_mod!(
-    #[allow(warnings)]
-    /// The parsing module
-    parser
-);
-
-pub use parser::*;
+pub use parser::{parse_expr, parse_program};
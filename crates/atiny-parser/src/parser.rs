@@ -0,0 +1,1467 @@
+//! A recursive descent parser that transforms a sequence of [Token]s into an [atiny_tree::abstract]
+//! tree.
+//!
+//! Infix operators are parsed into flat [ExprKind::Operators] sequences that are resolved by the
+//! [crate::fixity] pass after the whole program is parsed. They bind looser than the [PREFIX]
+//! operators.
+//!
+//! Syntax errors are collected instead of aborting the parse. When an error happens, the parser
+//! unwinds up to the closest recovery point (a statement, a match clause or a top level
+//! declaration), skips tokens until a synchronization token and inserts an error node in the tree.
+//!
+//! Every alternative that is tried at a token is remembered as an [Expected], so the error for an
+//! unexpected token can tell what would be accepted in its place.
+
+use std::collections::HashMap;
+
+use atiny_error::Error;
+use atiny_location::{Byte, ByteRange, Located};
+use atiny_tree::r#abstract::*;
+
+use crate::error::{Expected, SyntaxError};
+use crate::fixity::{self, Resolver, PREFIX};
+use crate::lexer::{Lexer, Spanned, Token};
+
+/// Marks that a syntax error was already reported, so the parser must unwind up to the closest
+/// recovery point.
+pub struct Reported;
+
+type Result<T> = std::result::Result<T, Reported>;
+
+/// Parses an entire atiny program. It returns all the declarations that could be parsed and every
+/// syntax error that was found.
+pub fn parse_program(code: &str) -> (Vec<TopLevel>, Vec<Error>) {
+    let mut parser = Parser::new(code);
+    let mut program = parser.program();
+
+    let mut resolver = Resolver::new(&program);
+    resolver.program(&mut program);
+    parser.errors.extend(resolver.errors);
+
+    parser.finish(program)
+}
+
+/// Parses a single atiny expression. It returns an [ExprKind::Error] if the expression cannot be
+/// recovered and every syntax error that was found.
+pub fn parse_expr(code: &str) -> (Expr, Vec<Error>) {
+    let mut parser = Parser::new(code);
+    let start = parser.start();
+
+    let mut expr = parser.expr().unwrap_or_else(|_| {
+        parser.index = parser.tokens.len();
+        parser.located(start, ExprKind::Error)
+    });
+
+    let mut resolver = Resolver::new(&[]);
+    resolver.expr(&mut expr);
+    parser.errors.extend(resolver.errors);
+
+    parser.finish(expr)
+}
+
+pub struct Parser<'a> {
+    tokens: Vec<Spanned<'a>>,
+    docs: HashMap<usize, String>,
+    index: usize,
+    last_end: Byte,
+    errors: Vec<SyntaxError>,
+    expected: Vec<Expected>,
+    expected_at: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(code: &'a str) -> Self {
+        let mut lexer = Lexer::new(code);
+        let mut tokens = Vec::new();
+        let mut docs = HashMap::new();
+        let mut lines = Vec::new();
+
+        // Documentation comments are taken out of the token stream and attached to the token that
+        // follows them, so they are ignored in the places where no documentation is expected.
+        for (token, location) in lexer.by_ref() {
+            if let Token::DocComment(line) = token {
+                lines.push(line.strip_prefix(' ').unwrap_or(line));
+            } else {
+                if !lines.is_empty() {
+                    docs.insert(tokens.len(), lines.join("\n"));
+                    lines.clear();
+                }
+                tokens.push((token, location));
+            }
+        }
+
+        Self {
+            tokens,
+            docs,
+            index: 0,
+            last_end: Byte(0),
+            errors: lexer.errors,
+            expected: Vec::new(),
+            expected_at: 0,
+        }
+    }
+
+    /// Reports the tokens that were not consumed and returns the result with all the errors.
+    pub fn finish<T>(mut self, result: T) -> (T, Vec<Error>) {
+        if self.index < self.tokens.len() {
+            let _ = self.unexpected::<()>();
+        }
+
+        self.errors.sort_by_key(|error| error.location().0 .0);
+
+        (result, self.errors.into_iter().map(Error::from).collect())
+    }
+
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.peek_nth(0)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<&Token<'a>> {
+        self.tokens.get(self.index + n).map(|(token, _)| token)
+    }
+
+    fn at(&self, token: &Token) -> bool {
+        self.peek() == Some(token)
+    }
+
+    /// Remembers that `expected` would be accepted at the current token. The alternatives of a
+    /// previous token are forgotten once the parser moves forward.
+    fn expecting(&mut self, expected: Expected) {
+        if self.expected_at != self.index {
+            self.expected.clear();
+            self.expected_at = self.index;
+        }
+
+        if !self.expected.contains(&expected) {
+            self.expected.push(expected);
+        }
+    }
+
+    /// Like [Self::at], but remembers the token as an expected alternative.
+    fn check(&mut self, token: &Token<'static>) -> bool {
+        self.expecting(Expected::Token(token.clone()));
+        self.at(token)
+    }
+
+    /// The documentation comments written right before the current token.
+    fn doc(&self) -> Doc {
+        self.docs.get(&self.index).cloned()
+    }
+
+    /// The start of the current token, it is used as the start of a [Located] node.
+    fn start(&self) -> Byte {
+        self.tokens
+            .get(self.index)
+            .map_or(self.last_end, |(_, location)| location.0)
+    }
+
+    /// Creates a [Located] node that goes from `start` to the end of the last consumed token.
+    fn located<T>(&self, start: Byte, data: T) -> Located<T> {
+        let end = Byte(usize::max(start.0, self.last_end.0));
+        Located::new(ByteRange(start, end), data)
+    }
+
+    /// Reports the current token as unexpected, together with the alternatives that were tried at
+    /// it. Errors in the same place are reported once, as the recovery may try to parse the same
+    /// token again.
+    fn unexpected<T>(&mut self) -> Result<T> {
+        let expected = if self.expected_at == self.index {
+            self.expected.clone()
+        } else {
+            Vec::new()
+        };
+
+        let error = match self.tokens.get(self.index) {
+            Some((token, location)) => {
+                SyntaxError::UnrecognizedToken(token.to_string(), *location, expected)
+            }
+            None => SyntaxError::UnrecognizedEof(self.last_end, expected),
+        };
+
+        let last = self.errors.last().map(SyntaxError::location);
+
+        if last != Some(error.location()) {
+            self.errors.push(error);
+        }
+
+        Err(Reported)
+    }
+
+    /// Moves the parser forward to the token at `index`.
+    fn skip_to(&mut self, index: usize) {
+        if index > self.index {
+            self.index = index;
+            self.last_end = self.tokens[index - 1].1 .1;
+        }
+    }
+
+    /// Skips tokens until a `stop` token is found outside of the braces, brackets and parenthesis
+    /// opened while skipping. It also stops at a closing brace that was not opened and at declaration
+    /// keywords, so the enclosing recovery points can synchronize too. Returns if it stopped at a
+    /// `stop` token or at a closing brace.
+    fn synchronize(&mut self, stop: &Token) -> bool {
+        let mut opened = Vec::new();
+        let mut index = self.index;
+
+        let found = loop {
+            let Some((token, _)) = self.tokens.get(index) else {
+                break false;
+            };
+
+            match token {
+                _ if self.is_declaration_start(index) => break false,
+                Token::LBrace => opened.push(Token::RBrace),
+                Token::LPar => opened.push(Token::RPar),
+                Token::LBracket => opened.push(Token::RBracket),
+                Token::RBrace | Token::RPar | Token::RBracket => {
+                    match opened.iter().rposition(|c| c == token) {
+                        Some(position) => opened.truncate(position),
+                        None if *token == Token::RBrace => break true,
+                        None => {}
+                    }
+                }
+                token if opened.is_empty() && token == stop => break true,
+                _ => {}
+            }
+
+            index += 1;
+        };
+
+        self.skip_to(index);
+        found
+    }
+
+    /// Skips tokens until the start of the next declaration, always skipping at least one token.
+    fn synchronize_top_level(&mut self, from: usize) {
+        let mut index = usize::max(self.index, from + 1);
+
+        while index < self.tokens.len() && !self.is_declaration_start(index) {
+            index += 1;
+        }
+
+        self.skip_to(usize::min(index, self.tokens.len()));
+    }
+
+    /// If a top level declaration starts at the token in `index`. A fixity keyword only starts one
+    /// when it's followed by a precedence, otherwise it's a name.
+    fn is_declaration_start(&self, index: usize) -> bool {
+        let token_at = |index| self.tokens.get(index).map(|(token, _)| token);
+
+        match token_at(index) {
+            Some(token) if token.fixity().is_some() => {
+                matches!(token_at(index + 1), Some(Token::Num(_)))
+            }
+            Some(token) => token.is_declaration_start(),
+            None => false,
+        }
+    }
+
+    fn bump(&mut self) -> Result<Spanned<'a>> {
+        let Some(spanned) = self.tokens.get(self.index).cloned() else {
+            return self.unexpected();
+        };
+
+        self.index += 1;
+        self.last_end = spanned.1 .1;
+        Ok(spanned)
+    }
+
+    fn eat(&mut self, token: &Token<'static>) -> bool {
+        let found = self.check(token);
+        if found {
+            let _ = self.bump();
+        }
+        found
+    }
+
+    fn expect(&mut self, token: &Token<'static>) -> Result<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            self.unexpected()
+        }
+    }
+
+    fn lower_id(&mut self) -> Result<String> {
+        self.expecting(Expected::LowerId);
+
+        match self.peek() {
+            Some(Token::LowerId(id)) => {
+                let id = id.to_string();
+                self.bump()?;
+                Ok(id)
+            }
+            _ => self.unexpected(),
+        }
+    }
+
+    fn upper_id(&mut self) -> Result<String> {
+        self.expecting(Expected::UpperId);
+
+        match self.peek() {
+            Some(Token::UpperId(id)) => {
+                let id = id.to_string();
+                self.bump()?;
+                Ok(id)
+            }
+            _ => self.unexpected(),
+        }
+    }
+
+    /// Parses a list of items separated by `sep` until the `close` token, returning whether the
+    /// list is empty or has a trailing separator.
+    fn sep<T>(
+        &mut self,
+        sep: &Token<'static>,
+        close: &Token<'static>,
+        mut item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<(Vec<T>, bool)> {
+        let mut vec = Vec::new();
+
+        loop {
+            if self.eat(close) {
+                return Ok((vec, true));
+            }
+
+            vec.push(item(self)?);
+
+            if !self.eat(sep) {
+                self.expect(close)?;
+                return Ok((vec, false));
+            }
+        }
+    }
+
+    fn block_list<T>(
+        &mut self,
+        sep: &Token<'static>,
+        item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        self.expect(&Token::LBrace)?;
+        Ok(self.sep(sep, &Token::RBrace, item)?.0)
+    }
+
+    /// Parses a list between braces that recovers from errors in its items. The broken items are
+    /// replaced by the result of `error` with the location of the skipped tokens.
+    fn recovering_block_list<T>(
+        &mut self,
+        sep: &Token<'static>,
+        mut item: impl FnMut(&mut Self) -> Result<T>,
+        error: impl Fn(ByteRange) -> T,
+    ) -> Result<Vec<T>> {
+        self.expect(&Token::LBrace)?;
+        let mut vec = Vec::new();
+
+        loop {
+            if self.eat(&Token::RBrace) {
+                return Ok(vec);
+            }
+
+            let start = self.start();
+
+            match item(self) {
+                Ok(item) => vec.push(item),
+                Err(Reported) => {
+                    let found = self.synchronize(sep);
+                    vec.push(error(self.located(start, ()).location));
+
+                    if !found {
+                        return Err(Reported);
+                    }
+                }
+            }
+
+            // A missing separator is reported and then treated as if it was there.
+            if !self.eat(sep) && !self.check(&Token::RBrace) {
+                let _ = self.unexpected::<()>();
+
+                if self.peek().is_none() {
+                    return Err(Reported);
+                }
+            }
+        }
+    }
+
+    /// Primary constructions that are used both for [Pattern] and [Expr].
+    fn atom<T>(&mut self, inner: impl FnMut(&mut Self) -> Result<Located<T>>) -> Result<Located<T>>
+    where
+        T: From<AtomKind<Located<T>>>,
+    {
+        if !self.is_atom_start(0) {
+            return self.unexpected();
+        }
+
+        let start = self.start();
+
+        let data = match self.bump()?.0 {
+            Token::Wildcard => AtomKind::Wildcard.into(),
+            Token::Num(n) => AtomKind::Number(n).into(),
+            Token::Str(string) => AtomKind::String(string).into(),
+            Token::Char(char) => AtomKind::Char(char).into(),
+            Token::LowerId(id) | Token::UpperId(id) => AtomKind::Identifier(id.to_string()).into(),
+            Token::LPar => {
+                let (mut vec, trailing) = self.sep(&Token::Comma, &Token::RPar, inner)?;
+
+                match vec.len() {
+                    0 => AtomKind::unit().into(),
+                    1 if !trailing => vec.pop().unwrap().data,
+                    _ => AtomKind::Tuple(vec).into(),
+                }
+            }
+            _ => unreachable!(),
+        };
+
+        Ok(self.located(start, data))
+    }
+
+    fn is_atom_start(&self, n: usize) -> bool {
+        matches!(
+            self.peek_nth(n),
+            Some(
+                Token::Wildcard
+                    | Token::Num(_)
+                    | Token::Str(_)
+                    | Token::Char(_)
+                    | Token::LowerId(_)
+                    | Token::UpperId(_)
+                    | Token::LPar
+                    | Token::LBracket
+            )
+        )
+    }
+
+    /// Parses a list literal like `[a, b]` into applications of `cons` that end with `nil`. Each
+    /// cell goes from its element to the closing bracket and the `nil` is located at the bracket.
+    fn list<T>(
+        &mut self,
+        item: impl FnMut(&mut Self) -> Result<Located<T>>,
+        cons: impl Fn(Located<T>, Located<T>) -> T,
+        nil: T,
+    ) -> Result<Located<T>> {
+        let start = self.start();
+        self.expect(&Token::LBracket)?;
+        let (items, _) = self.sep(&Token::Comma, &Token::RBracket, item)?;
+
+        let end = self.last_end;
+        let bracket = ByteRange(Byte(end.0.saturating_sub(1)), end);
+        let mut list = Located::new(bracket, nil);
+
+        for item in items.into_iter().rev() {
+            let location = ByteRange(item.location.0, end);
+            list = Located::new(location, cons(item, list));
+        }
+
+        Ok(self.located(start, list.data))
+    }
+
+    // For expressions
+
+    pub fn expr(&mut self) -> Result<Expr> {
+        self.infix(true)
+    }
+
+    /// Parses a flat sequence of operands separated by infix operators.
+    fn infix(&mut self, records: bool) -> Result<Expr> {
+        let start = self.start();
+        let mut operands = vec![self.prefix(records)?];
+        let mut operators = Vec::new();
+
+        while let Some(Token::Operator(operator)) = self.peek() {
+            let operator = operator.to_string();
+            let (_, location) = self.bump()?;
+            operators.push(Located::new(location, operator));
+            operands.push(self.prefix(records)?);
+        }
+
+        self.expecting(Expected::Operator);
+
+        if operators.is_empty() {
+            Ok(operands.pop().unwrap())
+        } else {
+            Ok(self.located(start, ExprKind::Operators(operands, operators)))
+        }
+    }
+
+    fn prefix(&mut self, records: bool) -> Result<Expr> {
+        let start = self.start();
+
+        if matches!(
+            self.peek(),
+            Some(Token::Ref | Token::Operator(fixity::DEREF))
+        ) {
+            let (token, _) = self.bump()?;
+            let expr = Box::new(self.prefix(records)?);
+
+            let data = match token {
+                Token::Ref => ExprKind::Ref(expr),
+                _ => ExprKind::Deref(expr),
+            };
+
+            return Ok(self.located(start, data));
+        }
+
+        let name = match self.peek() {
+            Some(Token::Operator(operator)) => PREFIX
+                .iter()
+                .find(|(prefix, _)| prefix == operator)
+                .map(|(_, name)| *name),
+            _ => None,
+        };
+
+        let Some(name) = name else {
+            return self.inner(records);
+        };
+
+        let (_, location) = self.bump()?;
+        let expr = self.prefix(records)?;
+
+        let data = ExprKind::prefix(Located::new(location, name), expr);
+        Ok(self.located(start, data))
+    }
+
+    fn atom_expr(&mut self) -> Result<Expr> {
+        if self.is_section_start() {
+            let start = self.start();
+            let operator = self.section()?;
+            let name = fixity::function_name(&operator.data);
+            Ok(self.located(start, ExprKind::Atom(AtomKind::Identifier(name))))
+        } else if self.at(&Token::LBracket) {
+            let identifier = |name: &str| AtomKind::Identifier(name.to_string());
+
+            let cons = |head: Expr, tail: Expr| {
+                let location = ByteRange(head.location.0, tail.location.1);
+                ExprKind::infix(head, Located::new(location, "Cons"), tail)
+            };
+
+            self.list(Self::expr, cons, ExprKind::Atom(identifier("Nil")))
+        } else if let Some(Token::Hole(name)) = self.peek() {
+            let start = self.start();
+            let name = name.to_string();
+            self.bump()?;
+            Ok(self.located(start, ExprKind::Hole(name)))
+        } else {
+            self.atom(Self::expr)
+        }
+    }
+
+    fn is_expr_atom_start(&self) -> bool {
+        self.is_atom_start(0) || matches!(self.peek(), Some(Token::Hole(_)))
+    }
+
+    fn is_section_start(&self) -> bool {
+        self.at(&Token::LPar)
+            && matches!(self.peek_nth(1), Some(Token::Operator(_)))
+            && self.peek_nth(2) == Some(&Token::RPar)
+    }
+
+    /// Parses an operator between parenthesis like `(<>)`, returning the operator.
+    fn section(&mut self) -> Result<Located<String>> {
+        self.expect(&Token::LPar)?;
+        self.expecting(Expected::Operator);
+
+        let Some(Token::Operator(operator)) = self.peek() else {
+            return self.unexpected();
+        };
+
+        let operator = operator.to_string();
+        let (_, location) = self.bump()?;
+        self.expect(&Token::RPar)?;
+
+        Ok(Located::new(location, operator))
+    }
+
+    /// Parses a sequence of applications, returning if it's only a single atom.
+    fn call(&mut self) -> Result<(Expr, bool)> {
+        let start = self.start();
+        let mut fun = self.atom_expr()?;
+        let mut single = true;
+
+        while self.is_expr_atom_start() || self.at(&Token::Operator(fixity::DEREF)) {
+            let arg = self.argument()?;
+            fun = self.located(start, ExprKind::Application(Box::new(fun), Box::new(arg)));
+            single = false;
+        }
+
+        Ok((fun, single))
+    }
+
+    /// Parses an argument of an application. A dereference like `f !r` reads the atom after it.
+    fn argument(&mut self) -> Result<Expr> {
+        if !self.at(&Token::Operator(fixity::DEREF)) {
+            return self.atom_expr();
+        }
+
+        let start = self.start();
+        self.bump()?;
+        let expr = self.argument()?;
+        Ok(self.located(start, ExprKind::Deref(Box::new(expr))))
+    }
+
+    fn is_record_start(&self) -> bool {
+        self.at(&Token::LBrace)
+            && matches!(self.peek_nth(1), Some(Token::LowerId(_)))
+            && matches!(self.peek_nth(2), Some(Token::Equal))
+    }
+
+    /// Parses an expression that is not an infix operation. Record creations are only allowed when
+    /// `records` is set because they would be ambiguous with the clauses of a `match`.
+    fn inner(&mut self, records: bool) -> Result<Expr> {
+        self.expecting(Expected::Expression);
+        let start = self.start();
+
+        match self.peek() {
+            Some(Token::If) => self.if_expr(records),
+            Some(Token::Bar) => self.abstraction(records),
+            Some(Token::Match) => self.match_expr(records),
+            Some(Token::Handle) => self.handle_expr(),
+            Some(Token::LBrace) if self.is_record_start() => {
+                let fields = self.block_list(&Token::Comma, Self::expr_field)?;
+                Ok(self.located(start, ExprKind::Record(fields)))
+            }
+            Some(Token::LBrace) => self.block_expr(),
+            _ => {
+                let (call, single) = self.call()?;
+
+                if records && self.is_record_start() {
+                    let fields = self.block_list(&Token::Comma, Self::expr_field)?;
+                    let data = ExprKind::RecordCreation(Box::new(call), fields);
+                    Ok(self.located(start, data))
+                } else if self.eat(&Token::Colon) {
+                    let typ = self.type_node()?;
+                    let data = ExprKind::Annotation(Box::new(call), Box::new(typ));
+                    Ok(self.located(start, data))
+                } else if single && self.at(&Token::Dot) {
+                    let mut expr = call;
+
+                    while self.eat(&Token::Dot) {
+                        let name = self.lower_id()?;
+                        expr = self.located(start, ExprKind::Field(Box::new(expr), name));
+                    }
+
+                    Ok(expr)
+                } else {
+                    Ok(call)
+                }
+            }
+        }
+    }
+
+    /// Parses both `if let` and `if` expressions. The condition of an `if` cannot have records
+    /// because they would be ambiguous with its block.
+    fn if_expr(&mut self, records: bool) -> Result<Expr> {
+        let start = self.start();
+        self.expect(&Token::If)?;
+
+        let data = if self.eat(&Token::Let) {
+            let pattern = self.pattern()?;
+            self.expect(&Token::Equal)?;
+            let matcher = self.inner(records)?;
+
+            let (true_arm, else_arm) = self.if_arms(records)?;
+            ExprKind::if_let(pattern, matcher, true_arm, else_arm)
+        } else {
+            let cond = self.infix(false)?;
+
+            let (true_arm, else_arm) = self.if_arms(records)?;
+            ExprKind::if_else(cond, true_arm, else_arm)
+        };
+
+        Ok(self.located(start, data))
+    }
+
+    fn if_arms(&mut self, records: bool) -> Result<(Expr, Expr)> {
+        let true_arm = self.block_expr()?;
+        self.expect(&Token::Else)?;
+
+        let else_arm = if self.check(&Token::If) {
+            self.if_expr(records)?
+        } else {
+            self.block_expr()?
+        };
+
+        Ok((true_arm, else_arm))
+    }
+
+    fn abstraction(&mut self, records: bool) -> Result<Expr> {
+        let start = self.start();
+        self.expect(&Token::Bar)?;
+
+        let mut params = vec![self.lambda_param()?];
+
+        while self.eat(&Token::Comma) {
+            params.push(self.lambda_param()?);
+        }
+
+        self.expect(&Token::Bar)?;
+
+        let body = self.infix(records)?;
+        Ok(self.located(start, ExprKind::Abstraction(params, Box::new(body))))
+    }
+
+    fn lambda_param(&mut self) -> Result<Param> {
+        let pattern = self.alternative()?;
+
+        let typ = if self.check(&Token::Colon) {
+            Some(self.type_annotation()?)
+        } else {
+            None
+        };
+
+        Ok(Param::new(pattern, typ))
+    }
+
+    fn match_expr(&mut self, records: bool) -> Result<Expr> {
+        let start = self.start();
+        self.expect(&Token::Match)?;
+
+        let scrutinee = self.inner(records)?;
+        let clauses = self.recovering_block_list(&Token::Comma, Self::clause, |location| {
+            Clause::new(
+                Located::new(location, PatternKind::Error),
+                Located::new(location, ExprKind::Error),
+            )
+        })?;
+
+        Ok(self.located(start, ExprKind::Match(Box::new(scrutinee), clauses)))
+    }
+
+    /// Parses a `handle`, its expression can't have records because they would be ambiguous with
+    /// the clauses.
+    fn handle_expr(&mut self) -> Result<Expr> {
+        let start = self.start();
+        self.expect(&Token::Handle)?;
+
+        let expr = self.inner(false)?;
+        let clauses = self.block_list(&Token::Comma, Self::handler_clause)?;
+
+        Ok(self.located(start, ExprKind::Handle(Box::new(expr), clauses)))
+    }
+
+    /// Parses a clause of a `handle`, like `return x => x` or `put s k => k ()`.
+    fn handler_clause(&mut self) -> Result<HandlerClause> {
+        let start = self.start();
+        let name = self.lower_id()?;
+        let name = self.located(start, name);
+
+        let mut params = Vec::new();
+
+        while self.is_atom_start(0) {
+            params.push(Param::new(self.atom_pattern()?, None));
+        }
+
+        self.expect(&Token::FatArrow)?;
+        let body = self.expr()?;
+
+        Ok(HandlerClause { name, params, body })
+    }
+
+    fn clause(&mut self) -> Result<Clause> {
+        let pat = self.pattern()?;
+
+        let guard = if self.eat(&Token::If) {
+            Some(self.expr()?)
+        } else {
+            None
+        };
+
+        self.expect(&Token::FatArrow)?;
+        let expr = self.expr()?;
+        Ok(Clause::guarded(pat, guard, expr))
+    }
+
+    fn expr_field(&mut self) -> Result<ExprField> {
+        let name = self.lower_id()?;
+        self.expect(&Token::Equal)?;
+        let expr = self.expr()?;
+        Ok(ExprField { name, expr })
+    }
+
+    fn statement(&mut self) -> Result<Statement> {
+        let start = self.start();
+
+        let data = if self.at(&Token::Fn) {
+            StatementKind::LetRec(self.local_fns()?)
+        } else if self.eat(&Token::Let) {
+            if self.eat(&Token::Rec) {
+                let start = self.start();
+                let name = self.lower_id()?;
+                let name = self.located(start, name);
+                self.expect(&Token::Equal)?;
+                let value = self.expr()?;
+                StatementKind::LetRec(vec![RecBinding { name, value }])
+            } else {
+                let pattern = self.pattern()?;
+                self.expect(&Token::Equal)?;
+                StatementKind::Let(pattern, self.expr()?)
+            }
+        } else {
+            StatementKind::Expr(self.expr()?)
+        };
+
+        Ok(self.located(start, data))
+    }
+
+    /// Parses the consecutive `fn` declarations of a block, they are a single group of bindings
+    /// that can call each other.
+    fn local_fns(&mut self) -> Result<Vec<RecBinding>> {
+        let mut bindings = vec![self.local_fn()?];
+
+        while self.at(&Token::Semi) && self.peek_nth(1) == Some(&Token::Fn) {
+            self.bump()?;
+            bindings.push(self.local_fn()?);
+        }
+
+        Ok(bindings)
+    }
+
+    /// Parses a `fn` inside of a block into a lambda. The types of its parameters and of its body
+    /// are optional.
+    fn local_fn(&mut self) -> Result<RecBinding> {
+        let start = self.start();
+        self.expect(&Token::Fn)?;
+
+        let name_start = self.start();
+
+        let name = if self.check(&Token::LPar) {
+            fixity::function_name(&self.section()?.data)
+        } else {
+            self.lower_id()?
+        };
+
+        let name = self.located(name_start, name);
+
+        let mut params = Vec::new();
+
+        while self.check(&Token::LPar) || self.is_atom_start(0) {
+            params.push(self.fn_param()?);
+        }
+
+        if params.is_empty() {
+            let unit = Located::new(name.location, TypeKind::unit());
+            let wildcard = Located::new(name.location, PatternKind::Atom(AtomKind::Wildcard));
+            params.push(Param::new(wildcard, Some(unit)));
+        }
+
+        let ret = if self.check(&Token::Colon) {
+            Some(self.type_annotation()?)
+        } else {
+            None
+        };
+
+        let mut body = self.block_expr()?;
+
+        if let Some(ret) = ret {
+            let location = ByteRange(ret.location.0, body.location.1);
+            let annotation = ExprKind::Annotation(Box::new(body), Box::new(ret));
+            body = Located::new(location, annotation);
+        }
+
+        let value = self.located(start, ExprKind::Abstraction(params, Box::new(body)));
+        Ok(RecBinding { name, value })
+    }
+
+    /// A parameter of a function, like `x`, `(x: Int)` or `(a, b)`.
+    fn fn_param(&mut self) -> Result<Param> {
+        if !self.at(&Token::LPar) || self.peek_nth(1) == Some(&Token::RPar) {
+            return Ok(Param::new(self.atom_pattern()?, None));
+        }
+
+        let start = self.start();
+        self.bump()?;
+        let pattern = self.pattern()?;
+
+        if self.check(&Token::Colon) {
+            let typ = self.type_annotation()?;
+            self.expect(&Token::RPar)?;
+            return Ok(Param::new(pattern, Some(typ)));
+        }
+
+        // Without an annotation, it's a pattern between parenthesis or a tuple.
+        let pattern = if self.eat(&Token::Comma) {
+            let (mut patterns, _) = self.sep(&Token::Comma, &Token::RPar, Self::pattern)?;
+            patterns.insert(0, pattern);
+            self.located(start, PatternKind::Atom(AtomKind::Tuple(patterns)))
+        } else {
+            self.expect(&Token::RPar)?;
+            pattern
+        };
+
+        Ok(Param::new(pattern, None))
+    }
+
+    fn block_expr(&mut self) -> Result<Expr> {
+        let start = self.start();
+
+        let block = if self.at(&Token::LBrace) && self.peek_nth(1) == Some(&Token::RBrace) {
+            self.bump()?;
+            self.bump()?;
+
+            let unit = self.located(start, ExprKind::Atom(AtomKind::unit()));
+            vec![self.located(start, StatementKind::Expr(unit))]
+        } else {
+            self.recovering_block_list(&Token::Semi, Self::statement, |location| {
+                let error = Located::new(location, ExprKind::Error);
+                Located::new(location, StatementKind::Expr(error))
+            })?
+        };
+
+        Ok(self.located(start, ExprKind::Block(block)))
+    }
+
+    // For patterns
+
+    fn atom_pattern(&mut self) -> Result<Pattern> {
+        if self.at(&Token::LBracket) {
+            let nil = PatternKind::Constructor("Nil".to_string(), Vec::new());
+            self.list(Self::pattern, PatternKind::cons, nil)
+        } else {
+            self.atom(Self::pattern)
+        }
+    }
+
+    fn pattern(&mut self) -> Result<Pattern> {
+        let start = self.start();
+        let mut pattern = self.alternative()?;
+
+        while self.eat(&Token::Bar) {
+            let right = self.alternative()?;
+            let or = PatternKind::Or(Box::new(pattern), Box::new(right));
+            pattern = self.located(start, or);
+        }
+
+        Ok(pattern)
+    }
+
+    /// A pattern that is not an or-pattern, these ones need parenthesis where a `|` can follow
+    /// them, like in the parameters of a lambda. The `::` of lists associates to the right.
+    fn alternative(&mut self) -> Result<Pattern> {
+        let start = self.start();
+        let head = self.cons_operand()?;
+
+        if self.at(&Token::Operator("::")) {
+            self.bump()?;
+            let tail = self.alternative()?;
+            Ok(self.located(start, PatternKind::cons(head, tail)))
+        } else {
+            Ok(head)
+        }
+    }
+
+    fn cons_operand(&mut self) -> Result<Pattern> {
+        self.expecting(Expected::Pattern);
+        let start = self.start();
+
+        match self.peek() {
+            Some(Token::UpperId(_)) if self.peek_nth(1) == Some(&Token::LBrace) => {
+                self.record_pattern()
+            }
+            Some(Token::LowerId(_)) if self.peek_nth(1) == Some(&Token::Operator("@")) => {
+                let name = self.lower_id()?;
+                self.bump()?;
+                let pattern = self.alternative()?;
+                Ok(self.located(start, PatternKind::As(name, Box::new(pattern))))
+            }
+            Some(Token::UpperId(_)) if self.is_atom_start(1) => {
+                let name = self.upper_id()?;
+                let mut args = Vec::new();
+
+                while self.is_atom_start(0) {
+                    args.push(self.atom_pattern()?);
+                }
+
+                Ok(self.located(start, PatternKind::Constructor(name, args)))
+            }
+            _ => self.atom_pattern(),
+        }
+    }
+
+    fn record_pattern(&mut self) -> Result<Pattern> {
+        let start = self.start();
+        let name = self.upper_id()?;
+        self.expect(&Token::LBrace)?;
+
+        let mut fields = Vec::new();
+        let mut rest = false;
+
+        while !self.eat(&Token::RBrace) {
+            if self.eat(&Token::DotDot) {
+                rest = true;
+                self.expect(&Token::RBrace)?;
+                break;
+            }
+
+            fields.push(self.pattern_field()?);
+
+            if !self.eat(&Token::Comma) {
+                self.expect(&Token::RBrace)?;
+                break;
+            }
+        }
+
+        Ok(self.located(start, PatternKind::Record(name, fields, rest)))
+    }
+
+    fn pattern_field(&mut self) -> Result<PatternField> {
+        let start = self.start();
+        let name = self.lower_id()?;
+
+        let pat = if self.eat(&Token::Equal) {
+            self.pattern()?
+        } else {
+            let punned = PatternKind::Atom(AtomKind::Identifier(name.clone()));
+            self.located(start, punned)
+        };
+
+        Ok(PatternField { name, pat })
+    }
+
+    // For types
+
+    fn type_atom(&mut self) -> Result<TypeNode> {
+        if self.is_type_record_start() {
+            return self.type_record();
+        }
+
+        if !self.is_type_atom_start(0) {
+            return self.unexpected();
+        }
+
+        let start = self.start();
+
+        let data = match self.bump()?.0 {
+            Token::LowerId(name) | Token::UpperId(name) => TypeKind::Variable(VariableNode {
+                name: name.to_string(),
+            }),
+            Token::LPar => {
+                let (mut types, trailing) =
+                    self.sep(&Token::Comma, &Token::RPar, Self::type_node)?;
+
+                match types.len() {
+                    0 => TypeKind::unit(),
+                    1 if !trailing => types.pop().unwrap().data,
+                    _ => TypeKind::Tuple(TypeTupleNode { types }),
+                }
+            }
+            _ => unreachable!(),
+        };
+
+        Ok(self.located(start, data))
+    }
+
+    /// A record type starts with a field or with its row, like `{ | r }`, so the body of a
+    /// function is not taken as its return type.
+    fn is_type_record_start(&self) -> bool {
+        self.at(&Token::LBrace)
+            && match self.peek_nth(1) {
+                Some(Token::LowerId(_)) => self.peek_nth(2) == Some(&Token::Colon),
+                Some(Token::Bar) => {
+                    matches!(self.peek_nth(2), Some(Token::LowerId(_)))
+                        && self.peek_nth(3) == Some(&Token::RBrace)
+                }
+                _ => false,
+            }
+    }
+
+    /// Parses a record type like `{ name : String | r }`. It's not an argument of type
+    /// applications, because it would be ambiguous with the body of a function after its return
+    /// type, so it must be between parenthesis there.
+    fn type_record(&mut self) -> Result<TypeNode> {
+        let start = self.start();
+        self.expect(&Token::LBrace)?;
+        let mut fields = Vec::new();
+
+        while let Some(Token::LowerId(_)) = self.peek() {
+            let name = self.lower_id()?;
+            let typ = self.type_annotation()?;
+            fields.push((name, typ));
+
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+        }
+
+        let rest = if self.eat(&Token::Bar) {
+            let start = self.start();
+            let name = self.lower_id()?;
+            Some(self.located(start, name))
+        } else {
+            None
+        };
+
+        self.expect(&Token::RBrace)?;
+
+        let data = TypeKind::Record(TypeRecordNode { fields, rest });
+        Ok(self.located(start, data))
+    }
+
+    fn is_type_atom_start(&self, n: usize) -> bool {
+        matches!(
+            self.peek_nth(n),
+            Some(Token::LowerId(_) | Token::UpperId(_) | Token::LPar)
+        )
+    }
+
+    fn type_call(&mut self) -> Result<TypeNode> {
+        let start = self.start();
+
+        match self.peek() {
+            Some(Token::LowerId(_) | Token::UpperId(_)) if self.is_type_atom_start(1) => {
+                let fun = match self.peek() {
+                    Some(Token::LowerId(_)) => self.lower_id()?,
+                    _ => self.upper_id()?,
+                };
+                let mut args = Vec::new();
+
+                while self.is_type_atom_start(0) {
+                    args.push(self.type_atom()?);
+                }
+
+                let data = TypeKind::Application(TypeApplicationNode { fun, args });
+                Ok(self.located(start, data))
+            }
+            _ => self.type_atom(),
+        }
+    }
+
+    fn type_arrow(&mut self) -> Result<TypeNode> {
+        let start = self.start();
+        let left = self.type_call()?;
+
+        if self.eat(&Token::Arrow) {
+            let right = self.type_node()?;
+            let effects = self.effects()?;
+            let arrow = ArrowNode::with_effects(left, right, effects);
+            Ok(self.located(start, TypeKind::Arrow(arrow)))
+        } else {
+            Ok(left)
+        }
+    }
+
+    /// Parses the effects after a `!`, if there's one. They are a row variable like `e` or a list
+    /// of effects like `{State Int, Console | e}`.
+    fn effects(&mut self) -> Result<Option<EffectRowNode>> {
+        if !self.eat(&Token::Operator("!")) {
+            return Ok(None);
+        }
+
+        let rest = |this: &mut Self| {
+            let start = this.start();
+            let name = this.lower_id()?;
+            Ok(this.located(start, name))
+        };
+
+        if let Some(Token::LowerId(_)) = self.peek() {
+            let rest = Some(rest(self)?);
+            return Ok(Some(EffectRowNode {
+                effects: Vec::new(),
+                rest,
+            }));
+        }
+
+        self.expect(&Token::LBrace)?;
+        let mut effects = Vec::new();
+
+        while let Some(Token::UpperId(_)) = self.peek() {
+            effects.push(self.type_call()?);
+
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+        }
+
+        let rest = if self.eat(&Token::Bar) {
+            Some(rest(self)?)
+        } else {
+            None
+        };
+
+        self.expect(&Token::RBrace)?;
+        Ok(Some(EffectRowNode { effects, rest }))
+    }
+
+    fn type_node(&mut self) -> Result<TypeNode> {
+        self.expecting(Expected::Type);
+        let start = self.start();
+
+        if self.at(&Token::Forall) {
+            self.bump()?;
+            let args = self.params()?;
+
+            self.expect(&Token::Dot)?;
+            let body = Box::new(self.type_node()?);
+
+            Ok(self.located(start, TypeKind::Forall(ForallNode { args, body })))
+        } else {
+            self.type_arrow()
+        }
+    }
+
+    /// Parses the type variables bound by a `forall` or a type declaration.
+    fn params(&mut self) -> Result<Vec<String>> {
+        let mut params = Vec::new();
+
+        while let Some(Token::LowerId(_)) = self.peek() {
+            params.push(self.lower_id()?);
+        }
+
+        self.expecting(Expected::LowerId);
+        Ok(params)
+    }
+
+    fn type_annotation(&mut self) -> Result<TypeNode> {
+        self.expect(&Token::Colon)?;
+        self.type_node()
+    }
+
+    // Top level
+
+    fn constructor(&mut self) -> Result<Constructor> {
+        let doc = self.doc();
+        self.expect(&Token::Bar)?;
+        let name = self.upper_id()?;
+        let mut types = Vec::new();
+
+        while self.is_type_atom_start(0) {
+            types.push(self.type_atom()?);
+        }
+
+        Ok(Constructor { doc, name, types })
+    }
+
+    fn field(&mut self) -> Result<Field> {
+        let doc = self.doc();
+        let name = self.lower_id()?;
+        let ty = self.type_annotation()?;
+        Ok(Field { doc, name, ty })
+    }
+
+    fn type_decl(&mut self) -> Result<TypeDecl> {
+        let doc = self.doc();
+        self.expect(&Token::Type)?;
+        let name = self.upper_id()?;
+        let params = self.params()?;
+
+        self.expect(&Token::Equal)?;
+
+        let constructors = if self.check(&Token::Bar) {
+            let mut constructors = vec![self.constructor()?];
+
+            while self.check(&Token::Bar) {
+                constructors.push(self.constructor()?);
+            }
+
+            TypeDeclKind::Sum(constructors)
+        } else if self.check(&Token::LBrace) && !self.has_record_row() {
+            TypeDeclKind::Product(self.block_list(&Token::Comma, Self::field)?)
+        } else {
+            TypeDeclKind::Alias(self.type_node()?)
+        };
+
+        Ok(TypeDecl {
+            doc,
+            name,
+            params,
+            constructors,
+        })
+    }
+
+    /// If the braces at the current token close a record type with a row, like
+    /// `{ name : String | r }`. A declaration with them is an alias, and the other ones declare a
+    /// product type, so an alias of a closed record type must be between parenthesis.
+    fn has_record_row(&self) -> bool {
+        let mut depth = 0;
+
+        for (token, _) in &self.tokens[self.index..] {
+            match token {
+                Token::LBrace | Token::LPar | Token::LBracket => depth += 1,
+                Token::RBrace | Token::RPar | Token::RBracket => {
+                    depth -= 1;
+
+                    if depth == 0 {
+                        return false;
+                    }
+                }
+                Token::Bar if depth == 1 => return true,
+                _ => {}
+            }
+        }
+
+        false
+    }
+
+    fn fn_decl(&mut self) -> Result<FnDecl> {
+        let doc = self.doc();
+        self.expect(&Token::Fn)?;
+
+        let start = self.start();
+
+        let name = if self.check(&Token::LPar) {
+            fixity::function_name(&self.section()?.data)
+        } else {
+            self.lower_id()?
+        };
+
+        let name = self.located(start, name);
+
+        let mut params = Vec::new();
+
+        while self.check(&Token::LPar) || self.is_atom_start(0) {
+            params.push(self.fn_param()?);
+        }
+
+        let ret = if self.check(&Token::Colon) {
+            Some(self.type_annotation()?)
+        } else {
+            None
+        };
+
+        let effects = self.effects()?;
+        let constraints = self.where_clause()?;
+        let body = self.block_expr()?;
+
+        Ok(FnDecl::new(
+            doc,
+            name,
+            params,
+            ret,
+            effects,
+            constraints,
+            body,
+        ))
+    }
+
+    fn constraint(&mut self) -> Result<Constraint> {
+        let start = self.start();
+        let class = self.upper_id()?;
+        let class = self.located(start, class);
+        let typ = self.type_atom()?;
+        Ok(Constraint { class, typ })
+    }
+
+    /// Parses the constraints after a `where`, if there's one.
+    fn where_clause(&mut self) -> Result<Vec<Constraint>> {
+        let mut constraints = Vec::new();
+
+        if self.eat(&Token::Where) {
+            constraints.push(self.constraint()?);
+
+            while self.eat(&Token::Comma) {
+                constraints.push(self.constraint()?);
+            }
+        }
+
+        Ok(constraints)
+    }
+
+    fn class_decl(&mut self) -> Result<ClassDecl> {
+        let doc = self.doc();
+        self.expect(&Token::Class)?;
+        let name = self.upper_id()?;
+        let param = self.lower_id()?;
+        let methods = self.block_list(&Token::Comma, Self::field)?;
+
+        Ok(ClassDecl {
+            doc,
+            name,
+            param,
+            methods,
+        })
+    }
+
+    fn effect_decl(&mut self) -> Result<EffectDecl> {
+        let doc = self.doc();
+        self.expect(&Token::Effect)?;
+        let name = self.upper_id()?;
+        let params = self.params()?;
+        let operations = self.block_list(&Token::Comma, Self::field)?;
+
+        Ok(EffectDecl {
+            doc,
+            name,
+            params,
+            operations,
+        })
+    }
+
+    fn instance_decl(&mut self) -> Result<InstanceDecl> {
+        let doc = self.doc();
+        self.expect(&Token::Instance)?;
+        let head = self.constraint()?;
+        let constraints = self.where_clause()?;
+        let mut methods = Vec::new();
+
+        self.expect(&Token::LBrace)?;
+
+        while !self.eat(&Token::RBrace) {
+            methods.push(self.fn_decl()?);
+        }
+
+        Ok(InstanceDecl {
+            doc,
+            head,
+            constraints,
+            methods,
+        })
+    }
+
+    fn fixity_decl(&mut self) -> Result<FixityDecl> {
+        let associativity = self.bump()?.0.fixity().unwrap_or(Associativity::None);
+
+        self.expecting(Expected::Precedence);
+
+        let Some(&Token::Num(precedence)) = self.peek() else {
+            return self.unexpected();
+        };
+
+        let (_, location) = self.bump()?;
+
+        let precedence = u8::try_from(precedence)
+            .ok()
+            .filter(|precedence| *precedence <= fixity::MAX_PRECEDENCE)
+            .unwrap_or_else(|| {
+                self.errors.push(SyntaxError::InvalidPrecedence(location));
+                fixity::MAX_PRECEDENCE
+            });
+
+        let operator = self.section()?;
+
+        Ok(FixityDecl {
+            associativity,
+            precedence,
+            operator,
+        })
+    }
+
+    fn top_level(&mut self) -> Result<TopLevel> {
+        self.expecting(Expected::Declaration);
+        let start = self.start();
+
+        let data = match self.peek() {
+            Some(Token::Fn) => TopLevelKind::FnDecl(self.fn_decl()?),
+            Some(Token::Type) => TopLevelKind::TypeDecl(self.type_decl()?),
+            Some(Token::Class) => TopLevelKind::Class(self.class_decl()?),
+            Some(Token::Instance) => TopLevelKind::Instance(self.instance_decl()?),
+            Some(Token::Effect) => TopLevelKind::Effect(self.effect_decl()?),
+            Some(token) if token.fixity().is_some() => TopLevelKind::Fixity(self.fixity_decl()?),
+            _ => return self.unexpected(),
+        };
+
+        Ok(self.located(start, data))
+    }
+
+    /// Parses all the declarations of a program, the broken ones are skipped.
+    pub fn program(&mut self) -> Vec<TopLevel> {
+        let mut program = Vec::new();
+
+        while self.peek().is_some() {
+            let from = self.index;
+
+            match self.top_level() {
+                Ok(top_level) => program.push(top_level),
+                Err(Reported) => self.synchronize_top_level(from),
+            }
+        }
+
+        program
+    }
+}
@@ -3,43 +3,76 @@
 
 use atiny_checker::context::Ctx;
 use atiny_checker::infer::Infer;
-use atiny_parser::{error::from_lalrpop, ExprParser, ProgramParser};
+use atiny_parser::{parse_expr, parse_program};
 
 #[macro_use]
 extern crate atiny_tests;
 
 mk_test! { "/suite/expr/", |code, file_name| {
     let ctx = Ctx::default();
-    ExprParser::new()
-        .parse(&code)
-        .map_err(|x| vec![from_lalrpop(x)])
-        .map(|parsed| (parsed.infer(ctx.clone()), ctx.take_errors()))
-        .and_then(|((typ, _), errs)| errs.map_or_else(|| Ok(typ.to_string()), Err))
-        .unwrap_or_else(|errs| {
-            errs.into_iter().map(|x| x.with_code(&code, &file_name).to_string()).collect()
-        })
+    let (parsed, mut errs) = parse_expr(&code);
+    let (typ, _) = parsed.infer(ctx.clone());
+    errs.extend(ctx.take_errors().unwrap_or_default());
+
+    if errs.is_empty() {
+        typ.to_string()
+    } else {
+        errs.into_iter().map(|x| x.with_code(&code, &file_name).to_string()).collect()
+    }
 } }
 
 mk_test! { "/suite/parsing/", |code, file_name| {
     use itertools::Itertools;
+    use std::iter;
+
+    let (parsed, errs) = parse_program(&code);
+    let errs = errs.into_iter().map(|x| x.with_code(&code, &file_name).to_string());
+
+    iter::once(parsed.iter().join("\n")).chain(errs).collect::<String>()
+} }
+
+mk_test! { "/suite/signatures/", |code, file_name| {
+    use atiny_checker::types::DeclSignature;
+
+    let mut ctx = Ctx::default();
+    let prelude = ctx.signatures.types.clone();
+
+    let (parsed, mut errs) = parse_program(&code);
+    parsed.infer(&mut ctx);
+    errs.extend(ctx.take_errors().unwrap_or_default());
 
-    ProgramParser::new()
-        .parse(&code)
-        .map_err(from_lalrpop)
-        .map(|x| x.iter().map(|x| x.to_string()).join("\n"))
-        .unwrap_or_else(|err| err.with_code(&code, &file_name).to_string())
+    let types = ctx.signatures.types.iter().filter(|(name, _)| !prelude.contains_key(*name));
+    let kinds = types.map(|(name, sig)| format!("type {name} : {}\n", sig.kind));
+
+    let signatures = ctx.signatures.values.iter().filter_map(|(name, sig)| match sig {
+        DeclSignature::Function(fun) => Some(format!("{name} : {}\n", fun.entire_type)),
+        DeclSignature::Method(method) => Some(format!("{name} : {}\n", method.typ)),
+        DeclSignature::Operation(operation) => Some(format!("{name} : {}\n", operation.typ)),
+        DeclSignature::Constructor(_) => None,
+    });
+
+    let errs = errs.into_iter().map(|x| x.with_code(&code, &file_name).to_string());
+    kinds.chain(signatures).chain(errs).collect::<String>()
+} }
+
+mk_test! { "/suite/elaborated/", |code, file_name| {
+    let mut ctx = Ctx::default();
+
+    let (parsed, mut errs) = parse_program(&code);
+    let bodies = parsed.infer(&mut ctx);
+    errs.extend(ctx.take_errors().unwrap_or_default());
+
+    let bodies = bodies.iter().map(|body| format!("{body}\n"));
+    let errs = errs.into_iter().map(|x| x.with_code(&code, &file_name).to_string());
+    bodies.chain(errs).collect::<String>()
 } }
 
 mk_test! { "/suite/", |code, file_name| {
     let mut ctx = Ctx::default();
 
-    ProgramParser::new()
-        .parse(&code)
-        .map_err(|x| vec![from_lalrpop(x)])
-        .map(|parsed| parsed.infer(&mut ctx))
-        .map(|_| ctx.take_errors())
-        .and_then(|errs| errs.map_or_else(|| Ok(String::new()), Err))
-        .unwrap_or_else(|errs| {
-            errs.into_iter().map(|x| x.with_code(&code, &file_name).to_string()).collect()
-        })
+    let (parsed, mut errs) = parse_program(&code);
+    parsed.infer(&mut ctx);
+    errs.extend(ctx.take_errors().unwrap_or_default());
+
+    errs.into_iter().map(|x| x.with_code(&code, &file_name).to_string()).collect()
 } }
@@ -12,6 +12,8 @@ use itertools::Itertools;
 pub enum AtomKind<T> {
     Wildcard,
     Number(u64),
+    String(String),
+    Char(char),
     Tuple(Vec<T>),
     Identifier(String),
 }
@@ -27,6 +29,8 @@ impl<T: Display> Display for AtomKind<T> {
         match self {
             Self::Wildcard => write!(f, "_"),
             Self::Number(n) => write!(f, "{n}"),
+            Self::String(s) => write!(f, "{s:?}"),
+            Self::Char(c) => write!(f, "{c:?}"),
             Self::Tuple(t) => write!(f, "({})", t.iter().join(", ")),
             Self::Identifier(id) => write!(f, "{id}"),
         }
@@ -61,6 +65,11 @@ impl Display for ExprField {
 #[derive(Debug)]
 pub enum StatementKind {
     Let(Pattern, Expr),
+
+    /// Bindings that can refer to themselves and to each other. It comes from a `let rec` or from
+    /// a group of consecutive `fn` declarations inside of a block.
+    LetRec(Vec<RecBinding>),
+
     Expr(Expr),
 }
 
@@ -68,6 +77,7 @@ impl Display for StatementKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Let(n, v) => write!(f, "let {n} = {v}"),
+            Self::LetRec(bindings) => write!(f, "let rec {}", bindings.iter().join(" and ")),
             Self::Expr(e) => write!(f, "{e}"),
         }
     }
@@ -75,6 +85,19 @@ impl Display for StatementKind {
 
 pub type Statement = Located<StatementKind>;
 
+/// A binding of a [StatementKind::LetRec], a local `fn` is a binding to its lambda.
+#[derive(Debug)]
+pub struct RecBinding {
+    pub name: Located<String>,
+    pub value: Expr,
+}
+
+impl Display for RecBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}", self.name.data, self.value)
+    }
+}
+
 /// Expressions are language constructions that intrinsically contains a return value. E.g
 ///
 /// ```atiny
@@ -85,25 +108,67 @@ pub type Statement = Located<StatementKind>;
 pub enum ExprKind {
     Atom(AtomKind<Expr>),
     Match(Box<Expr>, Vec<Clause>),
-    Abstraction(String, Box<Expr>),
+    Abstraction(Vec<Param>, Box<Expr>),
     Application(Box<Expr>, Box<Expr>),
     Annotation(Box<Expr>, Box<TypeNode>),
     RecordCreation(Box<Expr>, Vec<ExprField>),
+
+    /// A record without a declared type, like `{ name = "Alice", age = 20 }`.
+    Record(Vec<ExprField>),
+
     Field(Box<Expr>, String),
     Block(Vec<Statement>),
+
+    /// Discharges the effect of the operations of the clauses that the expression performs, like
+    /// `handle e { return x => x, get () k => k 1 }`.
+    Handle(Box<Expr>, Vec<HandlerClause>),
+
+    /// Creates a mutable reference with the value, like `ref 0`.
+    Ref(Box<Expr>),
+
+    /// Reads the value of a reference, like `!r`.
+    Deref(Box<Expr>),
+
+    /// Writes a value to a reference, like `r := 1`.
+    Assign(Box<Expr>, Box<Expr>),
+
+    /// A typed hole, like `?todo`. The checker reports its type instead of failing.
+    Hole(String),
+
+    /// A flat sequence of operands separated by infix operators, like `a + b * c`. The parser
+    /// resolves it into applications once the fixity of every operator is known, so it never
+    /// reaches the type checker.
+    Operators(Vec<Expr>, Vec<Located<String>>),
+
+    /// A placeholder for an expression that could not be parsed.
+    Error,
 }
 
 impl Display for ExprKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Atom(a) => write!(f, "{}", a),
-            Self::Abstraction(p, e) => write!(f, "(|{p}| {e})"),
+            Self::Abstraction(p, e) => write!(f, "(|{}| {e})", p.iter().join(", ")),
             Self::Annotation(p, e) => write!(f, "({p} : {e})"),
             Self::Application(fu, a) => write!(f, "({fu} {a})"),
             Self::Match(e, c) => write!(f, "{e} {{{}}}", c.iter().join(", ")),
             Self::RecordCreation(n, fields) => write!(f, "{n} {{ {} }}", fields.iter().join(", ")),
+            Self::Record(fields) => write!(f, "{{ {} }}", fields.iter().join(", ")),
             Self::Field(e, n) => write!(f, "{e}.{n}"),
             Self::Block(b) => write!(f, "{{{}}}", b.iter().join("; ")),
+            Self::Handle(e, c) => write!(f, "(handle {e} {{{}}})", c.iter().join(", ")),
+            Self::Ref(e) => write!(f, "(ref {e})"),
+            Self::Deref(e) => write!(f, "!{e}"),
+            Self::Assign(r, e) => write!(f, "({r} := {e})"),
+            Self::Hole(name) => write!(f, "?{name}"),
+            Self::Operators(operands, operators) => {
+                write!(f, "({}", operands[0])?;
+                for (operator, operand) in operators.iter().zip(&operands[1..]) {
+                    write!(f, " {} {operand}", operator.data)?;
+                }
+                write!(f, ")")
+            }
+            Self::Error => write!(f, "<error>"),
         }
     }
 }
@@ -124,6 +189,21 @@ impl ExprKind {
         )
     }
 
+    /// Desugars an `if` into a match on the prelude `Bool`.
+    pub fn if_else(cond: Expr, true_arm: Expr, else_arm: Expr) -> Self {
+        let true_pat = Pattern {
+            location: cond.location,
+            data: PatternKind::Constructor("True".to_string(), Vec::new()),
+        };
+
+        Self::if_let(true_pat, cond, true_arm, else_arm)
+    }
+
+    pub fn prefix<T: Display>(prefix: Located<T>, expr: Expr) -> Self {
+        let call = prefix.map(|p| Self::Atom(AtomKind::Identifier(p.to_string())));
+        Self::Application(Box::new(call), Box::new(expr))
+    }
+
     pub fn infix<T: Display>(left: Expr, infix: Located<T>, right: Expr) -> Self {
         let location = ByteRange(left.location.0, infix.location.1);
         let call = infix.map(|i| Self::Atom(AtomKind::Identifier(i.to_string())));
@@ -145,18 +225,72 @@ pub type Expr = Located<ExprKind>;
 pub enum PatternKind {
     Atom(AtomKind<Pattern>),
     Constructor(String, Vec<Pattern>),
+
+    /// Matches if any of the two patterns matches. Both of them bind the same variables.
+    Or(Box<Pattern>, Box<Pattern>),
+
+    /// Binds the whole value matched by the pattern to a name, like `list @ Cons x xs`.
+    As(String, Box<Pattern>),
+
+    /// Destructs a record, like `User { name, age = 0, .. }`. The flag tells if the fields that
+    /// are not written are ignored with `..`.
+    Record(String, Vec<PatternField>, bool),
+
+    /// A placeholder for a pattern that could not be parsed.
+    Error,
 }
 
 impl Display for PatternKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(elements) = self.list_elements() {
+            return write!(f, "[{}]", elements.iter().join(", "));
+        }
+
         match self {
             Self::Atom(i) => write!(f, "{}", i),
+            Self::Constructor(name, args) if name == "Cons" && args.len() == 2 => {
+                let (head, tail) = (&args[0], &args[1]);
+
+                if head.data.is_atomic() {
+                    write!(f, "{head} :: ")?;
+                } else {
+                    write!(f, "({head}) :: ")?;
+                }
+
+                if matches!(tail.data, Self::Or(..)) {
+                    write!(f, "({tail})")
+                } else {
+                    write!(f, "{tail}")
+                }
+            }
             Self::Constructor(name, args) if args.is_empty() => {
                 write!(f, "{}", name)
             }
             Self::Constructor(name, args) => {
-                write!(f, "{name}{}", args.iter().map(|x| format!(" {x}")).join(""))
+                write!(f, "{name}")?;
+
+                for arg in args {
+                    if arg.data.is_atomic() {
+                        write!(f, " {arg}")?;
+                    } else {
+                        write!(f, " ({arg})")?;
+                    }
+                }
+
+                Ok(())
+            }
+            Self::Or(left, right) => write!(f, "{left} | {right}"),
+            Self::Record(name, fields, rest) => {
+                let rest = rest.then(|| "..".to_string());
+                let fields = fields
+                    .iter()
+                    .map(ToString::to_string)
+                    .chain(rest)
+                    .join(", ");
+                write!(f, "{name} {{ {fields} }}")
             }
+            Self::As(name, pat) => write!(f, "{name} @ {pat}"),
+            Self::Error => write!(f, "<error>"),
         }
     }
 }
@@ -167,6 +301,54 @@ impl PatternKind {
     pub fn is_variable(&self) -> bool {
         matches!(self, Self::Atom(AtomKind::Identifier(_)))
     }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::Error)
+    }
+
+    /// The `head :: tail` pattern of the prelude lists.
+    pub fn cons(head: Pattern, tail: Pattern) -> Self {
+        Self::Constructor("Cons".to_string(), vec![head, tail])
+    }
+
+    /// The elements of a chain of `Cons` that ends with `Nil`, it's displayed as a list literal.
+    pub fn list_elements(&self) -> Option<Vec<&Pattern>> {
+        match self {
+            Self::Atom(AtomKind::Identifier(name)) if name == "Nil" => Some(Vec::new()),
+            Self::Constructor(name, args) if name == "Nil" && args.is_empty() => Some(Vec::new()),
+            Self::Constructor(name, args) if name == "Cons" && args.len() == 2 => {
+                let mut elements = args[1].data.list_elements()?;
+                elements.insert(0, &args[0]);
+                Some(elements)
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks if the pattern can be displayed as an argument of a constructor without parenthesis.
+    pub fn is_atomic(&self) -> bool {
+        match self {
+            Self::Constructor(_, args) => args.is_empty() || self.list_elements().is_some(),
+            Self::Or(..) | Self::As(..) | Self::Record(..) => false,
+            Self::Atom(_) | Self::Error => true,
+        }
+    }
+}
+
+/// A field of a record pattern. The field `name` alone is a shorthand for `name = name`.
+#[derive(Debug, Clone)]
+pub struct PatternField {
+    pub name: String,
+    pub pat: Pattern,
+}
+
+impl Display for PatternField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.pat.data {
+            PatternKind::Atom(AtomKind::Identifier(id)) if *id == self.name => write!(f, "{id}"),
+            pat => write!(f, "{} = {pat}", self.name),
+        }
+    }
 }
 
 /// Creates a wildcard pattern
@@ -177,23 +359,81 @@ pub fn wildcard() -> Pattern {
     }
 }
 
-/// This is a clause of a pattern match declaration. It contains a pattern and an expression that is
-/// the result of the match.
+/// This is a clause of a pattern match declaration. It contains a pattern, an optional guard that
+/// must be true for the clause to be chosen and an expression that is the result of the match.
 #[derive(Debug)]
 pub struct Clause {
     pub pat: Pattern,
+    pub guard: Option<Expr>,
     pub expr: Expr,
 }
 
 impl Clause {
     pub fn new(pat: Pattern, expr: Expr) -> Self {
-        Self { pat, expr }
+        Self::guarded(pat, None, expr)
+    }
+
+    pub fn guarded(pat: Pattern, guard: Option<Expr>, expr: Expr) -> Self {
+        Self { pat, guard, expr }
     }
 }
 
 impl Display for Clause {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} => {}", self.pat, self.expr)
+        write!(f, "{}", self.pat)?;
+
+        if let Some(guard) = &self.guard {
+            write!(f, " if {guard}")?;
+        }
+
+        write!(f, " => {}", self.expr)
+    }
+}
+
+/// A parameter of a lambda. It's an irrefutable pattern with an optional type annotation.
+#[derive(Debug)]
+pub struct Param {
+    pub pat: Pattern,
+    pub typ: Option<TypeNode>,
+}
+
+impl Param {
+    pub fn new(pat: Pattern, typ: Option<TypeNode>) -> Self {
+        Self { pat, typ }
+    }
+}
+
+impl Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pat)?;
+
+        if let Some(typ) = &self.typ {
+            write!(f, ": {typ}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A clause of a `handle`, it's either the `return` clause that receives the value of the handled
+/// expression or an operation with its parameters and the continuation.
+#[derive(Debug)]
+pub struct HandlerClause {
+    pub name: Located<String>,
+    pub params: Vec<Param>,
+    pub body: Expr,
+}
+
+impl Display for HandlerClause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params = self.params.iter().map(|param| format!(" {param}"));
+        write!(
+            f,
+            "{}{} => {}",
+            self.name.data,
+            params.format(""),
+            self.body
+        )
     }
 }
 
@@ -201,20 +441,51 @@ impl Display for Clause {
 pub struct ArrowNode {
     pub left: Box<TypeNode>,
     pub right: Box<TypeNode>,
+
+    /// The effects that are performed when the function is applied, it's pure without them.
+    pub effects: Option<EffectRowNode>,
 }
 
 impl ArrowNode {
     pub fn new(left: TypeNode, right: TypeNode) -> Self {
+        Self::with_effects(left, right, None)
+    }
+
+    pub fn with_effects(left: TypeNode, right: TypeNode, effects: Option<EffectRowNode>) -> Self {
         Self {
             left: Box::new(left),
             right: Box::new(right),
+            effects,
         }
     }
 }
 
 impl Display for ArrowNode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "({} -> {})", self.left, self.right)
+        match &self.effects {
+            Some(effects) => write!(f, "({} -> {} ! {effects})", self.left, self.right),
+            None => write!(f, "({} -> {})", self.left, self.right),
+        }
+    }
+}
+
+/// The effects of a function, like `{State Int | e}`. The type variable after the bar stands for
+/// the other effects, without it there are no other effects.
+#[derive(Debug)]
+pub struct EffectRowNode {
+    pub effects: Vec<TypeNode>,
+    pub rest: Option<Located<String>>,
+}
+
+impl Display for EffectRowNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let effects = self.effects.iter().join(", ");
+
+        match &self.rest {
+            Some(rest) if effects.is_empty() => write!(f, "{}", rest.data),
+            Some(rest) => write!(f, "{{{effects} | {}}}", rest.data),
+            None => write!(f, "{{{effects}}}"),
+        }
     }
 }
 
@@ -267,6 +538,30 @@ impl Display for TypeTupleNode {
     }
 }
 
+/// The type of the records that have the fields, like `{ name : String | r }`. The type variable
+/// after the bar stands for the other fields, without it there are no other fields.
+#[derive(Debug)]
+pub struct TypeRecordNode {
+    pub fields: Vec<(String, TypeNode)>,
+    pub rest: Option<Located<String>>,
+}
+
+impl Display for TypeRecordNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields = self.fields.iter();
+        let fields = fields
+            .map(|(name, typ)| format!("{name} : {typ}"))
+            .join(", ");
+
+        match &self.rest {
+            Some(rest) if fields.is_empty() => write!(f, "{{ | {} }}", rest.data),
+            Some(rest) => write!(f, "{{ {fields} | {} }}", rest.data),
+            None if fields.is_empty() => write!(f, "{{}}"),
+            None => write!(f, "{{ {fields} }}"),
+        }
+    }
+}
+
 /// The representation of a type in the AST.
 #[derive(Debug)]
 pub enum TypeKind {
@@ -275,6 +570,7 @@ pub enum TypeKind {
     Forall(ForallNode),
     Application(TypeApplicationNode),
     Tuple(TypeTupleNode),
+    Record(TypeRecordNode),
 }
 
 impl TypeKind {
@@ -293,14 +589,30 @@ impl Display for TypeKind {
             Self::Forall(n) => write!(f, "{}", n),
             Self::Application(n) => write!(f, "{}", n),
             Self::Tuple(n) => write!(f, "{}", n),
+            Self::Record(n) => write!(f, "{}", n),
         }
     }
 }
 
 pub type TypeNode = Located<TypeKind>;
 
+/// The text of the `///` comments written right before a declaration.
+pub type Doc = Option<String>;
+
+/// Displays the documentation of a declaration in front of it.
+struct DisplayDoc<'a>(&'a Doc);
+
+impl Display for DisplayDoc<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0
+            .as_ref()
+            .map_or(Ok(()), |doc| write!(f, "(doc {doc:?}) "))
+    }
+}
+
 #[derive(Debug)]
 pub struct Constructor {
+    pub doc: Doc,
     pub name: String,
     pub types: Vec<TypeNode>,
 }
@@ -309,14 +621,17 @@ impl Display for Constructor {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "| {} {}",
+            "| {}{} {}",
+            DisplayDoc(&self.doc),
             self.name,
             self.types.iter().map(|x| format!("({})", x)).join(" ")
         )
     }
 }
+
 #[derive(Debug)]
 pub struct Field {
+    pub doc: Doc,
     pub name: String,
     pub ty: TypeNode,
 }
@@ -325,10 +640,14 @@ pub struct Field {
 pub enum TypeDeclKind {
     Sum(Vec<Constructor>),
     Product(Vec<Field>),
+
+    /// Another name for a type, like `type Pair a = (a, a)`.
+    Alias(TypeNode),
 }
 
 #[derive(Debug)]
 pub struct TypeDecl {
+    pub doc: Doc,
     pub name: String,
     pub params: Vec<String>,
     pub constructors: TypeDeclKind,
@@ -336,13 +655,60 @@ pub struct TypeDecl {
 
 impl TypeDecl {
     pub fn unit() -> Self {
+        Self::sum("()", &["()"])
+    }
+
+    /// The prelude `Bool` type.
+    pub fn bool() -> Self {
+        Self::sum("Bool", &["True", "False"])
+    }
+
+    /// The prelude `List` type, that is built by the list literals and patterns.
+    pub fn list() -> Self {
+        let node = |data| Located::new(ByteRange::default(), data);
+        let elem = || {
+            node(TypeKind::Variable(VariableNode {
+                name: "t".to_string(),
+            }))
+        };
+
+        let list = node(TypeKind::Application(TypeApplicationNode {
+            fun: "List".to_string(),
+            args: vec![elem()],
+        }));
+
+        let cons = Constructor {
+            doc: None,
+            name: "Cons".to_string(),
+            types: vec![elem(), list],
+        };
+
+        let nil = Constructor {
+            doc: None,
+            name: "Nil".to_string(),
+            types: Vec::new(),
+        };
+
         Self {
-            name: "()".to_string(),
+            doc: None,
+            name: "List".to_string(),
+            params: vec!["t".to_string()],
+            constructors: TypeDeclKind::Sum(vec![cons, nil]),
+        }
+    }
+
+    fn sum(name: &str, constructors: &[&str]) -> Self {
+        let constructors = constructors.iter().map(|name| Constructor {
+            doc: None,
+            name: name.to_string(),
+            types: Vec::new(),
+        });
+
+        Self {
+            doc: None,
+            name: name.to_string(),
             params: Vec::new(),
-            constructors: TypeDeclKind::Sum(vec![Constructor {
-                name: "()".to_string(),
-                types: Vec::new(),
-            }]),
+            constructors: TypeDeclKind::Sum(constructors.collect()),
         }
     }
 }
@@ -350,59 +716,99 @@ impl TypeDecl {
 impl Display for TypeDecl {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let params = self.params.iter().map(|x| format!(" {x}")).join("");
+        let doc = DisplayDoc(&self.doc);
 
         match &self.constructors {
             TypeDeclKind::Sum(constructors) => write!(
                 f,
-                "(type {name}{params} (sum {constructors}))",
+                "{doc}(type {name}{params} (sum {constructors}))",
                 name = self.name,
                 params = params,
                 constructors = constructors.iter().join(" ")
             ),
             TypeDeclKind::Product(fields) => write!(
                 f,
-                "(type {name}{params} (product {fields}))",
+                "{doc}(type {name}{params} (product {fields}))",
                 name = self.name,
                 params = params,
                 fields = fields
                     .iter()
-                    .map(|x| format!("({} : {})", x.name, x.ty))
+                    .map(|x| format!("({}{} : {})", DisplayDoc(&x.doc), x.name, x.ty))
                     .join(" ")
             ),
+            TypeDeclKind::Alias(typ) => write!(
+                f,
+                "{doc}(type {name}{params} (alias {typ}))",
+                name = self.name,
+                params = params,
+            ),
         }
     }
 }
 
+/// Requires a type to be an instance of a class, like `Show a`.
+#[derive(Debug)]
+pub struct Constraint {
+    pub class: Located<String>,
+    pub typ: TypeNode,
+}
+
+impl Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.class.data, self.typ)
+    }
+}
+
 #[derive(Debug)]
 pub struct FnDecl {
+    pub doc: Doc,
     pub name: String,
-    pub params: Vec<(Pattern, TypeNode)>,
-    pub ret: TypeNode,
+    pub params: Vec<Param>,
+    pub ret: Option<TypeNode>,
+
+    /// The effects that the body can perform, a function with a written return type without them
+    /// is pure.
+    pub effects: Option<EffectRowNode>,
+
+    /// The constraints of the `where` clause, they are required from the callers.
+    pub constraints: Vec<Constraint>,
     pub body: Expr,
 }
 
 impl FnDecl {
     pub fn new(
+        doc: Doc,
         name: Located<String>,
-        mut params: Vec<(Pattern, TypeNode)>,
+        mut params: Vec<Param>,
         ret: Option<TypeNode>,
+        effects: Option<EffectRowNode>,
+        constraints: Vec<Constraint>,
         body: Expr,
     ) -> Self {
         let loc = name.location;
         if params.is_empty() {
-            params = vec![(
+            params = vec![Param::new(
                 Located::new(loc, PatternKind::Atom(AtomKind::Wildcard)),
-                Located::new(loc, TypeKind::unit()),
+                Some(Located::new(loc, TypeKind::unit())),
             )];
         }
 
         Self {
+            doc,
             name: name.data,
             params,
-            ret: ret.unwrap_or_else(|| Located::new(loc, TypeKind::unit())),
+            ret,
+            effects,
+            constraints,
             body,
         }
     }
+
+    /// Checks if the types of all the parameters and of the return are written, otherwise the
+    /// type of the function is inferred.
+    pub fn is_annotated(&self) -> bool {
+        self.ret.is_some() && self.params.iter().all(|param| param.typ.is_some())
+    }
 }
 
 impl Display for FnDecl {
@@ -410,21 +816,174 @@ impl Display for FnDecl {
         let params = self
             .params
             .iter()
-            .map(|(n, t)| format!("({} : {})", n, t))
+            .map(|param| {
+                let typ = param.typ.as_ref().map(|typ| format!(" : {typ}"));
+                format!("({}{})", param.pat, typ.unwrap_or_default())
+            })
             .join(" ");
 
+        let ret = self
+            .ret
+            .as_ref()
+            .map_or_else(String::new, |ret| format!(" : {ret}"));
+
+        let effects = self
+            .effects
+            .as_ref()
+            .map_or_else(String::new, |effects| format!(" ! {effects}"));
+
         write!(
             f,
-            "fn {} {} : {} {}",
-            self.name, params, self.ret, self.body
+            "{}fn {} {}{}{}{} {}",
+            DisplayDoc(&self.doc),
+            self.name,
+            params,
+            ret,
+            effects,
+            DisplayWhere(&self.constraints),
+            self.body
         )
     }
 }
 
+/// Displays a `where` clause if there are constraints.
+struct DisplayWhere<'a>(&'a [Constraint]);
+
+impl Display for DisplayWhere<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            write!(f, " where {}", self.0.iter().join(", "))
+        }
+    }
+}
+
+/// A class of types that implement its methods, like `class Show a { show : a -> String }`.
+#[derive(Debug)]
+pub struct ClassDecl {
+    pub doc: Doc,
+    pub name: String,
+    pub param: String,
+    pub methods: Vec<Field>,
+}
+
+impl Display for ClassDecl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let methods = self
+            .methods
+            .iter()
+            .map(|x| format!("({}{} : {})", DisplayDoc(&x.doc), x.name, x.ty))
+            .join(" ");
+
+        write!(
+            f,
+            "{}(class {} {} {})",
+            DisplayDoc(&self.doc),
+            self.name,
+            self.param,
+            methods
+        )
+    }
+}
+
+/// The methods of a class for a type constructor applied to type variables, like
+/// `instance Show (List a) where Show a { ... }`.
+#[derive(Debug)]
+pub struct InstanceDecl {
+    pub doc: Doc,
+    pub head: Constraint,
+
+    /// The constraints on the type variables of the head, they are required to use the instance.
+    pub constraints: Vec<Constraint>,
+    pub methods: Vec<FnDecl>,
+}
+
+impl Display for InstanceDecl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}(instance {}{} {})",
+            DisplayDoc(&self.doc),
+            self.head,
+            DisplayWhere(&self.constraints),
+            self.methods.iter().map(|x| format!("({x})")).join(" ")
+        )
+    }
+}
+
+/// An effect and its operations, like `effect State s { get : () -> s, put : s -> () }`. The
+/// operations are functions that perform the effect.
+#[derive(Debug)]
+pub struct EffectDecl {
+    pub doc: Doc,
+    pub name: String,
+    pub params: Vec<String>,
+    pub operations: Vec<Field>,
+}
+
+impl Display for EffectDecl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params = self.params.iter().map(|x| format!(" {x}")).join("");
+        let operations = self
+            .operations
+            .iter()
+            .map(|x| format!("({}{} : {})", DisplayDoc(&x.doc), x.name, x.ty))
+            .join(" ");
+
+        write!(
+            f,
+            "{}(effect {}{} {})",
+            DisplayDoc(&self.doc),
+            self.name,
+            params,
+            operations
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right,
+    None,
+}
+
+impl Associativity {
+    /// The keyword that declares an operator with this associativity.
+    pub const fn keyword(self) -> &'static str {
+        match self {
+            Self::Left => "infixl",
+            Self::Right => "infixr",
+            Self::None => "infix",
+        }
+    }
+}
+
+/// Declares how an infix operator groups with the others, e.g. `infixl 6 (<>)`. Operators with a
+/// higher precedence bind tighter.
+#[derive(Debug)]
+pub struct FixityDecl {
+    pub associativity: Associativity,
+    pub precedence: u8,
+    pub operator: Located<String>,
+}
+
+impl Display for FixityDecl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keyword = self.associativity.keyword();
+        write!(f, "({keyword} {} {})", self.precedence, self.operator.data)
+    }
+}
+
 #[derive(Debug)]
 pub enum TopLevelKind {
     TypeDecl(TypeDecl),
     FnDecl(FnDecl),
+    Fixity(FixityDecl),
+    Class(ClassDecl),
+    Instance(InstanceDecl),
+    Effect(EffectDecl),
 }
 
 /// It's a declaration on the top level of the program. It can be a function definition, a type
@@ -436,6 +995,10 @@ impl Display for TopLevelKind {
         match self {
             Self::TypeDecl(td) => write!(f, "{}", td),
             Self::FnDecl(fd) => write!(f, "{}", fd),
+            Self::Fixity(fx) => write!(f, "{}", fx),
+            Self::Class(cd) => write!(f, "{}", cd),
+            Self::Instance(id) => write!(f, "{}", id),
+            Self::Effect(ed) => write!(f, "{}", ed),
         }
     }
 }
@@ -1,27 +1,76 @@
 //! This module defines a tree that contains semantic information. It's widely used for code
 //! generation and some expansions.
 
+use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::fmt::{Display, Formatter};
+use std::rc::Rc;
 
-#[derive(Debug)]
+use itertools::Itertools;
+
+#[derive(Debug, Clone)]
 pub struct Symbol(pub String);
 
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The methods of a class for some type, that are passed to the functions with constraints.
+#[derive(Debug, Clone)]
+pub enum Dictionary {
+    /// An instance together with the dictionaries of its constraints.
+    Instance(Symbol, Vec<Self>),
+
+    /// A dictionary that is received by the function.
+    Param(Symbol),
+
+    /// A dictionary that is found when the constraints are solved. It stays empty if the
+    /// constraint has no solution.
+    Hole(Rc<RefCell<Option<Self>>>),
+}
+
+impl Display for Dictionary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Instance(name, args) if args.is_empty() => write!(f, "{name}"),
+            Self::Instance(name, args) => {
+                write!(f, "({name} {})", args.iter().join(" "))
+            }
+            Self::Param(name) => write!(f, "{name}"),
+            Self::Hole(hole) => match &*hole.borrow() {
+                Some(dictionary) => write!(f, "{dictionary}"),
+                None => write!(f, "?"),
+            },
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct VariableNode<T> {
     pub inst_types: Vec<T>,
     pub name: Symbol,
+
+    /// The dictionaries of the constraints of the variable, in the order of its type scheme.
+    pub dictionaries: Vec<Dictionary>,
 }
 
 #[derive(Debug)]
 pub enum Stmt<T> {
     Let(CaseTreeNode, Expr<T>),
+
+    /// Functions that are bound before their values, so they can call themselves and each other.
+    LetRec(Vec<(Symbol, Expr<T>)>),
+
     Expr(Expr<T>),
 }
 
 #[derive(Debug)]
 pub enum Expr<T> {
     Number(u64),
+    String(String),
+    Char(char),
     Tuple(Vec<Expr<T>>),
     Variable(VariableNode<T>),
 
@@ -34,14 +83,34 @@ pub enum Expr<T> {
     RecordUpdate(Box<Expr<T>>, Vec<(Symbol, Expr<T>)>),
     RecordField(Symbol, Box<Expr<T>>, Symbol),
 
+    /// A record without a declared type, its fields are in the order that they are written.
+    Record(Vec<(Symbol, Self)>),
+
+    /// The field of a record that is only known by its row, together with the type of the record.
+    RowField(Box<Self>, Symbol, T),
+
     Block(Vec<Stmt<T>>),
 
+    /// Handles the effect performed by an expression. The clauses are functions of the parameters
+    /// of the operations and of their continuations, the `return` one receives the value.
+    Handle(Box<Self>, Symbol, Vec<(Symbol, Self)>),
+
+    /// Allocates a mutable reference with the value.
+    Ref(Box<Self>),
+
+    /// Reads the value of a reference.
+    Deref(Box<Self>),
+
+    /// Writes the value to the reference, it's the unit.
+    Assign(Box<Self>, Box<Self>),
+
     Error,
 }
 
 #[derive(Debug)]
 pub struct CaseTree<T> {
     pub places: Vec<Expr<T>>,
+    pub guards: Vec<Option<Expr<T>>>,
     pub tree: CaseTreeNode,
 }
 
@@ -49,6 +118,12 @@ pub struct CaseTree<T> {
 pub enum CaseTreeNode {
     Node(Vec<(Symbol, CaseTreeNode)>),
     Leaf(usize),
+
+    /// Goes to the place if its guard is true, otherwise falls through to the next tree.
+    Guard(usize, Box<Self>),
+
+    /// Destructs a record into its fields, in this order, that are matched by the next tree.
+    Record(Vec<Symbol>, Box<Self>),
 }
 
 impl CaseTreeNode {
@@ -61,6 +136,30 @@ impl CaseTreeNode {
                 }
             }
             Self::Leaf(index) => writeln!(f, "{:indent$}{}", "", index, indent = indent)?,
+            Self::Record(fields, next) => {
+                let fields = fields
+                    .iter()
+                    .map(|Symbol(name)| name.as_str())
+                    .collect::<Vec<_>>();
+                writeln!(
+                    f,
+                    "{:indent$}{{{}}}:",
+                    "",
+                    fields.join(", "),
+                    indent = indent
+                )?;
+                next.render_indented(f, indent + 2)?;
+            }
+            Self::Guard(index, next) => {
+                writeln!(
+                    f,
+                    "{:indent$}{} if guard, else:",
+                    "",
+                    index,
+                    indent = indent
+                )?;
+                next.render_indented(f, indent + 2)?;
+            }
         }
         Ok(())
     }
@@ -72,4 +171,75 @@ impl Display for CaseTreeNode {
     }
 }
 
-pub struct FnBody<T>(pub Expr<T>);
+/// A function of the program that receives the dictionaries of its constraints before the
+/// arguments. The functions of the same group share the dictionaries, so they call each other
+/// without them.
+pub struct FnBody<T> {
+    pub name: Symbol,
+    pub dictionaries: Vec<Symbol>,
+    pub body: Expr<T>,
+}
+
+impl<T> Display for FnBody<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let dictionaries = self.dictionaries.iter().map(|x| format!(" [{x}]")).join("");
+        write!(f, "(fn {}{} {})", self.name, dictionaries, self.body)
+    }
+}
+
+impl<T> Display for Expr<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let fields = |fields: &[(Symbol, Self)]| {
+            fields
+                .iter()
+                .map(|(name, expr)| format!("{name} = {expr}"))
+                .join(", ")
+        };
+
+        match self {
+            Self::Number(n) => write!(f, "{n}"),
+            Self::String(s) => write!(f, "{s:?}"),
+            Self::Char(c) => write!(f, "{c:?}"),
+            Self::Tuple(exprs) => write!(f, "({})", exprs.iter().join(", ")),
+            Self::Variable(var) if var.dictionaries.is_empty() => write!(f, "{}", var.name),
+            Self::Variable(var) => {
+                let dictionaries = var.dictionaries.iter().map(|x| format!(" [{x}]"));
+                write!(f, "({}{})", var.name, dictionaries.format(""))
+            }
+            Self::CaseTree(scrutinee, tree) => {
+                write!(f, "(match {scrutinee} {})", tree.places.iter().join(" "))
+            }
+            Self::Abstraction(params, body) => write!(f, "(|{}| {body})", params.iter().join(", ")),
+            Self::Application(fun, args, _) => write!(f, "({fun} {})", args.iter().join(" ")),
+            Self::RecordCreation(name, values) => write!(f, "({name} {{{}}})", fields(values)),
+            Self::RecordUpdate(expr, values) => write!(f, "({expr} {{{}}})", fields(values)),
+            Self::RecordField(_, expr, field) | Self::RowField(expr, field, _) => {
+                write!(f, "{expr}.{field}")
+            }
+            Self::Record(values) => write!(f, "{{{}}}", fields(values)),
+            Self::Block(statements) => write!(f, "{{{}}}", statements.iter().join("; ")),
+            Self::Handle(expr, effect, clauses) => {
+                write!(f, "(handle {effect} {expr} {{{}}})", fields(clauses))
+            }
+            Self::Ref(expr) => write!(f, "(ref {expr})"),
+            Self::Deref(expr) => write!(f, "!{expr}"),
+            Self::Assign(reference, expr) => write!(f, "({reference} := {expr})"),
+            Self::Error => write!(f, "<error>"),
+        }
+    }
+}
+
+impl<T> Display for Stmt<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Let(_, expr) => write!(f, "let _ = {expr}"),
+            Self::LetRec(bindings) => {
+                let bindings = bindings
+                    .iter()
+                    .map(|(name, expr)| format!("{name} = {expr}"));
+                write!(f, "let rec {}", bindings.format(", "))
+            }
+            Self::Expr(expr) => write!(f, "{expr}"),
+        }
+    }
+}